@@ -141,6 +141,10 @@ public class UwbInjector {
         return mUwbDiagnostics;
     }
 
+    public SystemBuildProperties getSystemBuildProperties() {
+        return mSystemBuildProperties;
+    }
+
     /**
      * Create a UwbShellCommand instance.
      */
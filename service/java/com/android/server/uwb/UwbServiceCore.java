@@ -450,11 +450,28 @@ public class UwbServiceCore implements INativeUwbManager.DeviceNotification,
         } catch (InterruptedException e) {
             e.printStackTrace();
         } catch (ExecutionException e) {
+            // Raised natively when the command policy disallows raw vendor commands.
+            if (e.getCause() instanceof SecurityException) {
+                throw (SecurityException) e.getCause();
+            }
             e.printStackTrace();
         }
         return status;
     }
 
+    /**
+     * Command classes the native layer may send to the UWBS. Raw vendor commands bypass the
+     * checks of the session APIs, so user builds do not allow them.
+     */
+    private int getCommandPolicy() {
+        int policy = NativeUwbManager.POLICY_ALLOW_DEVICE_CONTROL
+                | NativeUwbManager.POLICY_ALLOW_SESSION_CONTROL;
+        if (!mUwbInjector.getSystemBuildProperties().isUserBuild()) {
+            policy |= NativeUwbManager.POLICY_ALLOW_RAW_VENDOR_COMMAND;
+        }
+        return policy;
+    }
+
     private class EnableDisableTask extends Handler {
 
         EnableDisableTask(Looper looper) {
@@ -497,6 +514,7 @@ public class UwbServiceCore implements INativeUwbManager.DeviceNotification,
                 Log.i(TAG, "Initialization start ...");
                 mUwbWakeLock.acquire();
                 try {
+                    mNativeUwbManager.updatePolicy(getCommandPolicy());
                     if (!mNativeUwbManager.doInitialize()) {
                         Log.e(TAG, "Error enabling UWB");
                         mUwbMetrics.incrementDeviceInitFailureCount();
@@ -626,5 +644,9 @@ public class UwbServiceCore implements INativeUwbManager.DeviceNotification,
         pw.println("---- Dump of UwbServiceCore ----");
         pw.println("device state = " + getDeviceStateString(mState));
         pw.println("mLastStateChangedReason = " + mLastStateChangedReason);
+        pw.println("---- Native stack info ----");
+        pw.println(mNativeUwbManager.getNativeStackInfo());
+        pw.println("---- UCI command metrics ----");
+        pw.println(mNativeUwbManager.getUciMetrics());
     }
 }
@@ -0,0 +1,72 @@
+/*
+ * Copyright (C) 2022 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package com.android.server.uwb.data;
+
+import java.util.Arrays;
+import java.util.Objects;
+
+/**
+ * Contents of the CORE_GET_DEVICE_INFO_RSP reported by the UWBS.
+ */
+public class UwbDeviceInfoResponse {
+    public byte statusCode;
+    public int uciVersion;
+    public int macVersion;
+    public int phyVersion;
+    public int uciTestVersion;
+    public byte[] vendorSpecInfo;
+
+    public UwbDeviceInfoResponse(byte statusCode, int uciVersion, int macVersion,
+            int phyVersion, int uciTestVersion, byte[] vendorSpecInfo) {
+        this.statusCode = statusCode;
+        this.uciVersion = uciVersion;
+        this.macVersion = macVersion;
+        this.phyVersion = phyVersion;
+        this.uciTestVersion = uciTestVersion;
+        this.vendorSpecInfo = vendorSpecInfo;
+    }
+
+    @Override
+    public boolean equals(Object o) {
+        if (this == o) return true;
+        if (!(o instanceof UwbDeviceInfoResponse)) return false;
+        UwbDeviceInfoResponse that = (UwbDeviceInfoResponse) o;
+        return statusCode == that.statusCode
+                && uciVersion == that.uciVersion
+                && macVersion == that.macVersion
+                && phyVersion == that.phyVersion
+                && uciTestVersion == that.uciTestVersion
+                && Arrays.equals(vendorSpecInfo, that.vendorSpecInfo);
+    }
+
+    @Override
+    public int hashCode() {
+        return Objects.hash(statusCode, uciVersion, macVersion, phyVersion, uciTestVersion,
+                Arrays.hashCode(vendorSpecInfo));
+    }
+
+    @Override
+    public String toString() {
+        return "UwbDeviceInfoResponse{"
+                + "statusCode=" + statusCode
+                + ", uciVersion=" + uciVersion
+                + ", macVersion=" + macVersion
+                + ", phyVersion=" + phyVersion
+                + ", uciTestVersion=" + uciTestVersion
+                + ", vendorSpecInfo=" + Arrays.toString(vendorSpecInfo)
+                + '}';
+    }
+}
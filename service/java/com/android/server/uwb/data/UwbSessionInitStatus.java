@@ -0,0 +1,55 @@
+/*
+ * Copyright (C) 2021 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+package com.android.server.uwb.data;
+
+public class UwbSessionInitStatus {
+    public final byte status;
+    /**
+     * Sessions the UWBS reported as active when it rejected the session init with
+     * STATUS_CODE_ERROR_MAX_SESSIONS_EXCEEDED, i.e. the limit it enforces. -1 otherwise, or if the
+     * count could not be queried.
+     */
+    public final int uwbsActiveSessionCount;
+    /** Maximum number of sessions advertised by the JNI layer. */
+    public final int maxSessionNumber;
+
+    public UwbSessionInitStatus(byte status, int uwbsActiveSessionCount, int maxSessionNumber) {
+        this.status = status;
+        this.uwbsActiveSessionCount = uwbsActiveSessionCount;
+        this.maxSessionNumber = maxSessionNumber;
+    }
+
+    public byte getStatus() {
+        return status;
+    }
+
+    public int getUwbsActiveSessionCount() {
+        return uwbsActiveSessionCount;
+    }
+
+    public int getMaxSessionNumber() {
+        return maxSessionNumber;
+    }
+
+    @Override
+    public String toString() {
+        return "UwbSessionInitStatus { "
+                + " status = " + status
+                + ", uwbsActiveSessionCount = " + uwbsActiveSessionCount
+                + ", maxSessionNumber = " + maxSessionNumber
+                + " }";
+    }
+}
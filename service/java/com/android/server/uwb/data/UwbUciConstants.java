@@ -36,7 +36,11 @@ public class UwbUciConstants {
      * Table 13: Control Messages to Initialize UWB session
      */
     public static final byte SESSION_TYPE_RANGING = 0x00;
-    public static final byte SESSION_TYPE_DATA_TRANSFER = 0x01;
+    public static final byte SESSION_TYPE_RANGING_AND_IN_BAND_DATA = 0x01;
+    public static final byte SESSION_TYPE_DATA_TRANSFER = 0x02;
+    public static final byte SESSION_TYPE_RANGING_ONLY_PHASE = 0x03;
+    public static final byte SESSION_TYPE_IN_BAND_DATA_PHASE = 0x04;
+    public static final byte SESSION_TYPE_RANGING_WITH_DATA_PHASE = 0x05;
     public static final byte SESSION_TYPE_CCC = (byte) CCC;
     public static final byte SESSION_TYPE_DEVICE_TEST_MODE = (byte) 0xD0;
 
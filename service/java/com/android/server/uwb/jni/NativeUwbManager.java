@@ -20,8 +20,10 @@ import android.util.Log;
 
 import com.android.server.uwb.UwbInjector;
 import com.android.server.uwb.data.UwbConfigStatusData;
+import com.android.server.uwb.data.UwbDeviceInfoResponse;
 import com.android.server.uwb.data.UwbMulticastListUpdateStatus;
 import com.android.server.uwb.data.UwbRangingData;
+import com.android.server.uwb.data.UwbSessionInitStatus;
 import com.android.server.uwb.data.UwbTlvData;
 import com.android.server.uwb.data.UwbUciConstants;
 import com.android.server.uwb.data.UwbVendorUciResponse;
@@ -30,6 +32,16 @@ import com.android.server.uwb.info.UwbPowerStats;
 public class NativeUwbManager {
     private static final String TAG = NativeUwbManager.class.getSimpleName();
 
+    /** Command classes that can be allowed through {@link #updatePolicy(int)}. */
+    public static final int POLICY_ALLOW_DEVICE_CONTROL = 1 << 0;
+    public static final int POLICY_ALLOW_SESSION_CONTROL = 1 << 1;
+    public static final int POLICY_ALLOW_RAW_VENDOR_COMMAND = 1 << 2;
+
+    /** Returned by {@link #waitForSessionState} when the timeout expired. */
+    public static final byte SESSION_STATE_WAIT_TIMEOUT = -2;
+    /** Longest wait allowed by {@link #waitForSessionState}. */
+    public static final int MAX_WAIT_FOR_SESSION_STATE_MS = 10000;
+
     public final Object mSessionFnLock = new Object();
     public final Object mSessionCountFnLock = new Object();
     public final Object mGlobalStateFnLock = new Object();
@@ -137,19 +149,81 @@ public class NativeUwbManager {
         return nativeGetPowerStats();
     }
 
+    /**
+     * Retrieves a description of the native stack for bugreports, one "key: value" per line.
+     *
+     * @return : Native crate version and supported feature matrix, or null on failure
+     */
+    public String getNativeStackInfo() {
+        return nativeGetNativeStackInfo();
+    }
+
+    /**
+     * Retrieves the latency and failures of the UCI commands sent so far, one line per command.
+     *
+     * @return : Per-command metrics summary, or null on failure
+     */
+    public String getUciMetrics() {
+        return nativeGetUciMetrics();
+    }
+
+    /**
+     * Update the command classes the native layer is allowed to send to the UWBS.
+     *
+     * <p>Commands of a disallowed class are refused natively before reaching the UWBS, by throwing
+     * a {@link SecurityException} from the call that would send them.
+     *
+     * @param policy : Bitmask of POLICY_ALLOW_* values
+     */
+    public void updatePolicy(int policy) {
+        nativeUpdatePolicy(policy);
+    }
+
+    /**
+     * Retrieves the device info reported by the UWBS.
+     *
+     * @param refresh : Query the UWBS again instead of returning the cached device info
+     * @return : {@link UwbDeviceInfoResponse}, or null on failure
+     */
+    public UwbDeviceInfoResponse getDeviceInfo(boolean refresh) {
+        synchronized (mGlobalStateFnLock) {
+            return nativeGetDeviceInfo(refresh);
+        }
+    }
+
     /**
      * Creates the new UWB session with parameter session ID and type of the session.
      *
      * @param sessionId   : Session ID is 4 Octets unique random number generated by application
-     * @param sessionType : Type of session 0x00: Ranging session 0x01: Data transfer 0x02-0x9F: RFU
+     * @param sessionType : Type of session 0x00: Ranging session 0x01: Ranging and in-band data
+     *                    0x02: Data transfer 0x03: Ranging only phase 0x04: In-band data phase
+     *                    0x05: Ranging with data phase 0x06-0x9F: RFU
      *                    0xA0-0xCF: Reserved for Vendor Specific use case 0xD0: Device Test Mode
      *                    0xD1-0xDF: RFU 0xE0-0xFF: Vendor Specific use
      * @return : {@link UwbUciConstants}  Status code
      */
     public byte initSession(int sessionId, byte sessionType) {
+        return initSessionWithStatus(sessionId, sessionType).getStatus();
+    }
+
+    /**
+     * Creates the new UWB session, like {@link #initSession(int, byte)}, and reports the session
+     * counts if the UWBS ran out of sessions.
+     *
+     * @param sessionId   : Session ID is 4 Octets unique random number generated by application
+     * @param sessionType : Type of session, see {@link #initSession(int, byte)}
+     * @return : {@link UwbSessionInitStatus} Status code, with the number of sessions the UWBS
+     * reported as active when the status is STATUS_CODE_ERROR_MAX_SESSIONS_EXCEEDED
+     */
+    public UwbSessionInitStatus initSessionWithStatus(int sessionId, byte sessionType) {
+        UwbSessionInitStatus status;
         synchronized (mSessionFnLock) {
-            return nativeSessionInit(sessionId, sessionType);
+            status = nativeSessionInit(sessionId, sessionType);
+        }
+        if (status.getStatus() == UwbUciConstants.STATUS_CODE_ERROR_MAX_SESSIONS_EXCEEDED) {
+            Log.w(TAG, "initSession: UWBS out of sessions, " + status);
         }
+        return status;
     }
 
     /**
@@ -197,6 +271,25 @@ public class NativeUwbManager {
         }
     }
 
+    /**
+     * Waits until the UWB session reaches the target state or the timeout expires.
+     *
+     * <p>The session state is queried natively, with an interval growing from 50 ms to 1 s. Like
+     * {@link #getSessionState(int)}, each query holds the session status lock; the lock is
+     * released between queries.
+     *
+     * @param sessionId   : Session of the UWB session to wait for
+     * @param targetState : {@link UwbUciConstants}  Session State to wait for
+     * @param timeoutMs   : Maximum time to wait in milliseconds, capped at
+     *                    {@link #MAX_WAIT_FOR_SESSION_STATE_MS}
+     * @return : targetState once reached, {@link #SESSION_STATE_WAIT_TIMEOUT} if the timeout
+     * expired first, or -1 if the state could not be queried
+     */
+    public byte waitForSessionState(int sessionId, byte targetState, int timeoutMs) {
+        timeoutMs = Math.max(0, Math.min(timeoutMs, MAX_WAIT_FOR_SESSION_STATE_MS));
+        return nativeWaitForSessionState(sessionId, targetState, timeoutMs);
+    }
+
     /**
      * Starts a UWB session.
      *
@@ -237,6 +330,169 @@ public class NativeUwbManager {
         }
     }
 
+    /**
+     * Configure range data notifications for the requested UWB session.
+     *
+     * <p>The notification config, proximity bounds and AoA bounds are validated and packed into
+     * app configuration TLVs natively. The AoA bounds are only sent with a FiRa 2.0 config that
+     * uses them.
+     *
+     * @param sessionId          : Session ID for which notifications are configured
+     * @param rangeDataNtfConfig : RANGE_DATA_NTF_CONFIG value
+     * @param proximityNear      : RANGE_DATA_NTF_PROXIMITY_NEAR in cm
+     * @param proximityFar       : RANGE_DATA_NTF_PROXIMITY_FAR in cm, not less than proximityNear
+     * @param azimuthLower       : Lower AoA azimuth bound in degrees, from -180
+     * @param azimuthUpper       : Upper AoA azimuth bound in degrees, up to 180
+     * @param elevationLower     : Lower AoA elevation bound in degrees, from -90
+     * @param elevationUpper     : Upper AoA elevation bound in degrees, up to 90
+     * @return : {@link UwbUciConstants}  Status code
+     */
+    public byte configureRangeDataNtf(int sessionId, int rangeDataNtfConfig, int proximityNear,
+            int proximityFar, float azimuthLower, float azimuthUpper, float elevationLower,
+            float elevationUpper) {
+        synchronized (mSetAppConfigFnLock) {
+            return nativeConfigureRangeDataNtf(sessionId, rangeDataNtfConfig, proximityNear,
+                    proximityFar, azimuthLower, azimuthUpper, elevationLower, elevationUpper);
+        }
+    }
+
+    /**
+     * Reconfigure the requested UWB session to the full set of APP Configuration Parameters.
+     *
+     * <p>Only the parameters that differ from the values last applied to the session are sent to
+     * the UWBS. While the session is active, the change is rejected unless all of them can be
+     * reconfigured during ranging.
+     *
+     * @param sessionId       : Session ID to reconfigure
+     * @param appConfigParams : Encoded TLVs of the full APP Configuration Parameter set
+     * @return : {@link UwbTlvData} : Status of the update and the tlvs that were applied
+     */
+    public UwbTlvData reconfigureSession(int sessionId, byte[] appConfigParams) {
+        synchronized (mSetAppConfigFnLock) {
+            return nativeReconfigureSession(sessionId, appConfigParams);
+        }
+    }
+
+    /**
+     * Export the configuration of the requested UWB session as a portable blob.
+     *
+     * <p>The blob is versioned and carries the session type, the UCI version of the UWBS and the
+     * APP Configuration Parameters last applied to the session. Key material (VENDOR_ID,
+     * STATIC_STS_IV, SESSION_KEY and SUB_SESSION_KEY) is left out, so a session using static STS
+     * cannot be imported from the blob alone.
+     *
+     * @param sessionId : Session ID to export
+     * @return : Serialized session configuration, or null if the session is unknown
+     */
+    public byte[] exportSessionConfig(int sessionId) {
+        synchronized (mSetAppConfigFnLock) {
+            return nativeExportSessionConfig(sessionId);
+        }
+    }
+
+    /**
+     * Open a UWB session configured from a blob produced by {@link #exportSessionConfig(int)}.
+     *
+     * <p>The blob is rejected if it was exported from a UWBS with a different UCI major version,
+     * or if it asks for an STS mode or channel the local UWBS does not support. If the
+     * configuration is not accepted, the newly opened session is deinitialized again.
+     *
+     * @param sessionId : Session ID of the new session
+     * @param blob      : Serialized session configuration
+     * @return : {@link UwbUciConstants}  Status code
+     */
+    public byte importSessionConfig(int sessionId, byte[] blob) {
+        synchronized (mSessionFnLock) {
+            synchronized (mSetAppConfigFnLock) {
+                return nativeImportSessionConfig(sessionId, blob);
+            }
+        }
+    }
+
+    /**
+     * Opt the requested UWB session in or out of the idle block stride governor.
+     *
+     * <p>While the device is not interactive, opted in sessions use the given BLOCK_STRIDE_LENGTH
+     * and get their previous value back once it is interactive again. Opting out of a throttled
+     * session restores its block stride immediately.
+     *
+     * @param sessionId       : Session ID to govern
+     * @param idleBlockStride : BLOCK_STRIDE_LENGTH to use while not interactive, negative to opt
+     *                        out
+     * @return : {@link UwbUciConstants}  Status code
+     */
+    public byte setIdleBlockStride(int sessionId, int idleBlockStride) {
+        synchronized (mSetAppConfigFnLock) {
+            return nativeSetIdleBlockStride(sessionId, idleBlockStride);
+        }
+    }
+
+    /**
+     * Report the device interactive state to the idle block stride governor.
+     *
+     * @param interactive : Whether the device is interactive
+     */
+    public void setDeviceInteractive(boolean interactive) {
+        synchronized (mSetAppConfigFnLock) {
+            nativeSetDeviceInteractive(interactive);
+        }
+    }
+
+    /**
+     * Store a provisioned session profile natively.
+     *
+     * <p>The profile uses the blob format of {@link #exportSessionConfig(int)} and is validated
+     * before being stored. Profiles are kept in memory only and replace any profile with the same
+     * id.
+     *
+     * @param profileId : Profile ID
+     * @param blob      : Serialized session configuration
+     * @return : {@link UwbUciConstants}  Status code
+     */
+    public byte storeProfile(int profileId, byte[] blob) {
+        synchronized (mGlobalStateFnLock) {
+            return nativeStoreProfile(profileId, blob);
+        }
+    }
+
+    /**
+     * Lists the ids of the provisioned session profiles stored natively.
+     *
+     * @return : Profile IDs in ascending order
+     */
+    public int[] listProfiles() {
+        synchronized (mGlobalStateFnLock) {
+            return nativeListProfiles();
+        }
+    }
+
+    /**
+     * Remove a provisioned session profile.
+     *
+     * @param profileId : Profile ID
+     * @return : true if the profile was stored
+     */
+    public boolean removeProfile(int profileId) {
+        synchronized (mGlobalStateFnLock) {
+            return nativeRemoveProfile(profileId);
+        }
+    }
+
+    /**
+     * Open a UWB session configured from a provisioned profile.
+     *
+     * @param sessionId : Session ID of the new session
+     * @param profileId : Profile ID stored with {@link #storeProfile(int, byte[])}
+     * @return : {@link UwbUciConstants}  Status code
+     */
+    public byte openSessionWithProfile(int sessionId, int profileId) {
+        synchronized (mSessionFnLock) {
+            synchronized (mSetAppConfigFnLock) {
+                return nativeOpenSessionWithProfile(sessionId, profileId);
+            }
+        }
+    }
+
     /**
      * Get APP Configuration Parameters for the requested UWB session
      *
@@ -247,9 +503,45 @@ public class NativeUwbManager {
      */
     public UwbTlvData getAppConfigurations(int sessionId, int noOfParams, int appConfigParamLen,
             byte[] appConfigIds) {
+        return getAppConfigurations(sessionId, noOfParams, appConfigParamLen, appConfigIds,
+                false);
+    }
+
+    /**
+     * Get APP Configuration Parameters for the requested UWB session, optionally from the values
+     * last applied to the session instead of querying the UWBS.
+     *
+     * <p>Cached reads generate no UCI traffic and only return parameters that were successfully
+     * set since the session was initialized. An empty {@code appConfigIds} returns all of them.
+     *
+     * @param noOfParams        : The number (n) of APP Configuration Parameters
+     * @param appConfigParamLen : The length of APP Configuration Parameters
+     * @param appConfigIds      : APP Configuration Parameter
+     * @param cached            : Read the last applied values instead of querying the UWBS
+     * @return :  {@link UwbTlvData} : All tlvs that are to be decoded
+     */
+    public UwbTlvData getAppConfigurations(int sessionId, int noOfParams, int appConfigParamLen,
+            byte[] appConfigIds, boolean cached) {
         synchronized (mSetAppConfigFnLock) {
             return nativeGetAppConfigurations(sessionId, noOfParams, appConfigParamLen,
-                    appConfigIds);
+                    appConfigIds, cached);
+        }
+    }
+
+    /**
+     * Lists the APP Configuration Parameter IDs set on the requested UWB session, without their
+     * values and without querying the UWBS.
+     *
+     * <p>Each ID is paired with the status of its last update, {@link
+     * UwbUciConstants#STATUS_CODE_OK} if the UWBS confirmed it.
+     *
+     * @param sessionId : Session ID to inspect
+     * @return : {@link UwbConfigStatusData} : Pairs of (ID, status), or null if the session is
+     * unknown
+     */
+    public UwbConfigStatusData listConfiguredAppConfigIds(int sessionId) {
+        synchronized (mSetAppConfigFnLock) {
+            return nativeListConfiguredAppConfigIds(sessionId);
         }
     }
 
@@ -320,9 +612,17 @@ public class NativeUwbManager {
 
     private native int nativeGetMaxSessionNumber();
 
+    private native UwbDeviceInfoResponse nativeGetDeviceInfo(boolean refresh);
+
+    private native void nativeUpdatePolicy(int policy);
+
+    private native String nativeGetNativeStackInfo();
+
+    private native String nativeGetUciMetrics();
+
     private native byte nativeResetDevice(byte resetConfig);
 
-    private native byte nativeSessionInit(int sessionId, byte sessionType);
+    private native UwbSessionInitStatus nativeSessionInit(int sessionId, byte sessionType);
 
     private native byte nativeSessionDeInit(int sessionId);
 
@@ -334,11 +634,37 @@ public class NativeUwbManager {
 
     private native byte nativeGetSessionState(int sessionId);
 
+    private native byte nativeWaitForSessionState(int sessionId, byte targetState, int timeoutMs);
+
     private native UwbConfigStatusData nativeSetAppConfigurations(int sessionId, int noOfParams,
             int appConfigParamLen, byte[] appConfigParams);
 
     private native UwbTlvData nativeGetAppConfigurations(int sessionId, int noOfParams,
-            int appConfigParamLen, byte[] appConfigParams);
+            int appConfigParamLen, byte[] appConfigParams, boolean cached);
+
+    private native byte nativeConfigureRangeDataNtf(int sessionId, int rangeDataNtfConfig,
+            int proximityNear, int proximityFar, float azimuthLower, float azimuthUpper,
+            float elevationLower, float elevationUpper);
+
+    private native UwbTlvData nativeReconfigureSession(int sessionId, byte[] appConfigParams);
+
+    private native byte[] nativeExportSessionConfig(int sessionId);
+
+    private native byte nativeImportSessionConfig(int sessionId, byte[] blob);
+
+    private native byte nativeSetIdleBlockStride(int sessionId, int idleBlockStride);
+
+    private native void nativeSetDeviceInteractive(boolean interactive);
+
+    private native byte nativeStoreProfile(int profileId, byte[] blob);
+
+    private native int[] nativeListProfiles();
+
+    private native boolean nativeRemoveProfile(int profileId);
+
+    private native byte nativeOpenSessionWithProfile(int sessionId, int profileId);
+
+    private native UwbConfigStatusData nativeListConfiguredAppConfigIds(int sessionId);
 
     private native UwbTlvData nativeGetCapsInfo();
 
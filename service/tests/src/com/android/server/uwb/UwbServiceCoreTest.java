@@ -153,6 +153,7 @@ public class UwbServiceCoreTest {
     @Mock private UwbConfigurationManager mUwbConfigurationManager;
     @Mock private UwbInjector mUwbInjector;
     @Mock DeviceConfigFacade mDeviceConfigFacade;
+    @Mock SystemBuildProperties mSystemBuildProperties;
     private TestLooper mTestLooper;
     private MockitoSession mMockitoSession;
 
@@ -169,6 +170,7 @@ public class UwbServiceCoreTest {
         when(mUwbInjector.isSystemApp(TEST_UID, TEST_PACKAGE_NAME)).thenReturn(true);
         when(mUwbInjector.isForegroundAppOrService(TEST_UID, TEST_PACKAGE_NAME)).thenReturn(true);
         when(mUwbInjector.getDeviceConfigFacade()).thenReturn(mDeviceConfigFacade);
+        when(mUwbInjector.getSystemBuildProperties()).thenReturn(mSystemBuildProperties);
         when(mDeviceConfigFacade.getBugReportMinIntervalMs())
                 .thenReturn(DeviceConfigFacade.DEFAULT_BUG_REPORT_MIN_INTERVAL_MS);
         mUwbServiceCore = new UwbServiceCore(mContext, mNativeUwbManager, mUwbMetrics,
@@ -254,6 +256,25 @@ public class UwbServiceCoreTest {
                 StateChangeReason.SYSTEM_POLICY);
     }
 
+    @Test
+    public void testEnableUpdatesCommandPolicy() throws Exception {
+        when(mSystemBuildProperties.isUserBuild()).thenReturn(false);
+        enableUwb();
+
+        verify(mNativeUwbManager).updatePolicy(NativeUwbManager.POLICY_ALLOW_DEVICE_CONTROL
+                | NativeUwbManager.POLICY_ALLOW_SESSION_CONTROL
+                | NativeUwbManager.POLICY_ALLOW_RAW_VENDOR_COMMAND);
+    }
+
+    @Test
+    public void testEnableUpdatesCommandPolicyOnUserBuild() throws Exception {
+        when(mSystemBuildProperties.isUserBuild()).thenReturn(true);
+        enableUwb();
+
+        verify(mNativeUwbManager).updatePolicy(NativeUwbManager.POLICY_ALLOW_DEVICE_CONTROL
+                | NativeUwbManager.POLICY_ALLOW_SESSION_CONTROL);
+    }
+
     @Test
     public void testEnableWhenAlreadyEnabled() throws Exception {
         IUwbAdapterStateCallbacks cb = mock(IUwbAdapterStateCallbacks.class);
@@ -626,6 +647,21 @@ public class UwbServiceCoreTest {
         verify(vendorCb).onVendorResponseReceived(gid, oid, payload);
     }
 
+    @Test
+    public void testSendVendorUciCommandDisallowedByPolicy() throws Exception {
+        enableUwb();
+
+        when(mNativeUwbManager.sendRawVendorCmd(anyInt(), anyInt(), any()))
+                .thenThrow(new SecurityException());
+
+        try {
+            mUwbServiceCore.sendVendorUciMessage(0, 0, new byte[0]);
+            fail();
+        } catch (SecurityException e) {
+            // pass
+        }
+    }
+
     @Test
     public void testDeviceStateCallback() throws Exception {
         IUwbAdapterStateCallbacks cb = mock(IUwbAdapterStateCallbacks.class);
@@ -0,0 +1,169 @@
+//! Last app configs successfully applied to each session.
+//!
+//! The cache is updated whenever the chip accepts a set app config request and dropped when the
+//! session is deinitialized or the UWBS loses its state, so that configs can be read back without
+//! generating UCI traffic.
+//! Configs whose last update the chip did not confirm are tracked with the status it returned.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+pub struct AppConfigCache {
+    sessions: Mutex<HashMap<u32, SessionConfigs>>,
+}
+
+#[derive(Default)]
+struct SessionConfigs {
+    /// Session type the session was initialized with, if initialized through this layer.
+    session_type: Option<u8>,
+    configs: BTreeMap<u8, Vec<u8>>,
+    /// Status of the last update of each config the chip did not confirm.
+    unconfirmed: BTreeMap<u8, u8>,
+}
+
+impl AppConfigCache {
+    pub fn new() -> Self {
+        Self { sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// Start caching for a newly initialized session, dropping anything left from an earlier
+    /// session with the same id.
+    pub fn init_session(&self, session_id: u32, session_type: u8) {
+        self.sessions.lock().unwrap().insert(
+            session_id,
+            SessionConfigs { session_type: Some(session_type), ..Default::default() },
+        );
+    }
+
+    /// Return the type the session was initialized with.
+    pub fn session_type(&self, session_id: u32) -> Option<u8> {
+        self.sessions.lock().unwrap().get(&session_id)?.session_type
+    }
+
+    /// Record the TLVs applied to the session, replacing earlier values of the same configs.
+    pub fn update(&self, session_id: u32, tlvs: &[(u8, &[u8])]) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.entry(session_id).or_default();
+        for (cfg_id, value) in tlvs {
+            session.configs.insert(*cfg_id, value.to_vec());
+            session.unconfirmed.remove(cfg_id);
+        }
+    }
+
+    /// Record the status of configs the chip did not confirm. Their cached values, if any, are
+    /// left untouched. Nothing is recorded for an unknown session, the chip may not know it either.
+    pub fn record_unconfirmed(&self, session_id: u32, statuses: &[(u8, u8)]) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = match sessions.get_mut(&session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        for (cfg_id, status) in statuses {
+            session.unconfirmed.insert(*cfg_id, *status);
+        }
+    }
+
+    /// Return the id of every config set on the session in ascending order, each with the status
+    /// of its last update: 0 (ok) if the chip confirmed it. Returns None if the session is unknown.
+    pub fn configured_ids(&self, session_id: u32) -> Option<Vec<(u8, u8)>> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(&session_id)?;
+        let mut ids: BTreeMap<u8, u8> = session.configs.keys().map(|id| (*id, 0)).collect();
+        ids.extend(&session.unconfirmed);
+        Some(ids.into_iter().collect())
+    }
+
+    /// Return the cached values of the requested configs in request order, or of every cached
+    /// config when no id is requested. Configs that were never applied are left out. Returns None
+    /// if the session is unknown.
+    pub fn get(&self, session_id: u32, cfg_ids: &[u8]) -> Option<Vec<(u8, Vec<u8>)>> {
+        let sessions = self.sessions.lock().unwrap();
+        let configs = &sessions.get(&session_id)?.configs;
+        if cfg_ids.is_empty() {
+            return Some(configs.iter().map(|(id, value)| (*id, value.clone())).collect());
+        }
+        Some(
+            cfg_ids
+                .iter()
+                .filter_map(|id| configs.get(id).map(|value| (*id, value.clone())))
+                .collect(),
+        )
+    }
+
+    /// Return the number of sessions with cached configs.
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Forget everything cached for the session.
+    pub fn remove(&self, session_id: u32) {
+        self.sessions.lock().unwrap().remove(&session_id);
+    }
+
+    /// Forget everything cached for all sessions.
+    pub fn clear(&self) {
+        self.sessions.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_and_get() {
+        let cache = AppConfigCache::new();
+        assert!(cache.get(1, &[]).is_none());
+
+        cache.update(1, &[(0x02, &[0x00][..]), (0x27, &[0x08, 0x07][..])]);
+        cache.update(1, &[(0x02, &[0x01][..])]);
+        assert_eq!(cache.get(1, &[]).unwrap(), vec![(0x02, vec![0x01]), (0x27, vec![0x08, 0x07])]);
+        assert_eq!(
+            cache.get(1, &[0x27, 0x11, 0x02]).unwrap(),
+            vec![(0x27, vec![0x08, 0x07]), (0x02, vec![0x01])]
+        );
+        assert!(cache.get(2, &[0x02]).is_none());
+    }
+
+    #[test]
+    fn test_init_session() {
+        let cache = AppConfigCache::new();
+        cache.update(1, &[(0x02, &[0x00][..])]);
+        assert_eq!(cache.session_type(1), None);
+
+        cache.init_session(1, 0x00);
+        assert_eq!(cache.session_type(1), Some(0x00));
+        assert!(cache.get(1, &[]).unwrap().is_empty());
+        assert_eq!(cache.session_type(2), None);
+    }
+
+    #[test]
+    fn test_configured_ids() {
+        let cache = AppConfigCache::new();
+        assert!(cache.configured_ids(1).is_none());
+        cache.record_unconfirmed(1, &[(0x04, 0x04)]);
+        assert!(cache.configured_ids(1).is_none());
+        assert_eq!(cache.session_count(), 0);
+
+        cache.update(1, &[(0x02, &[0x00][..]), (0x04, &[0x09][..])]);
+        cache.record_unconfirmed(1, &[(0x04, 0x04), (0x11, 0x01)]);
+        assert_eq!(cache.configured_ids(1).unwrap(), vec![(0x02, 0), (0x04, 0x04), (0x11, 0x01)]);
+        // The previously confirmed value is kept.
+        assert_eq!(cache.get(1, &[0x04]).unwrap(), vec![(0x04, vec![0x09])]);
+
+        cache.update(1, &[(0x11, &[0x00][..])]);
+        assert_eq!(cache.configured_ids(1).unwrap(), vec![(0x02, 0), (0x04, 0x04), (0x11, 0)]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let cache = AppConfigCache::new();
+        cache.update(1, &[(0x02, &[0x00][..])]);
+        cache.update(2, &[(0x02, &[0x01][..])]);
+        assert_eq!(cache.session_count(), 2);
+        cache.remove(1);
+        assert_eq!(cache.session_count(), 1);
+        assert!(cache.get(1, &[]).is_none());
+        assert_eq!(cache.get(2, &[]).unwrap(), vec![(0x02, vec![0x01])]);
+    }
+}
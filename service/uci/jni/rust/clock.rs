@@ -0,0 +1,67 @@
+//! Time source for native timeouts and intervals.
+//!
+//! Time dependent code takes a Clock instead of calling Instant::now() and thread::sleep()
+//! directly, so that tests can drive it with FakeClock instead of waiting in real time.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The clock used in production, backed by the monotonic system clock.
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// A clock that only moves when slept on or advanced explicitly.
+#[cfg(test)]
+pub struct FakeClock {
+    now: std::sync::Mutex<Instant>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new() -> Self {
+        Self { now: std::sync::Mutex::new(Instant::now()) }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::new();
+        let start = clock.now();
+        clock.sleep(Duration::from_millis(20));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.now() - start, Duration::from_millis(1020));
+    }
+}
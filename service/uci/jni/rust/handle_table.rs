@@ -0,0 +1,112 @@
+//! Opaque handles for native objects whose lifetime is controlled by the Java layer.
+//!
+//! Java only ever stores a handle. The boxed object stays owned by the table until its handle is
+//! removed, so a stale or forged handle coming back through JNI is rejected instead of being
+//! dereferenced. Handles are never reused.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use jni::sys::jlong;
+
+pub struct HandleTable<T> {
+    inner: Mutex<HandleTableInner>,
+    // The table only stores addresses, T is never shared through it.
+    _marker: PhantomData<fn() -> T>,
+}
+
+struct HandleTableInner {
+    next_handle: jlong,
+    objects: HashMap<jlong, usize>,
+}
+
+impl<T> HandleTable<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(HandleTableInner { next_handle: 1, objects: HashMap::new() }),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Take ownership of the object and return the handle to hand out to Java.
+    pub fn insert(&self, object: Box<T>) -> jlong {
+        let mut inner = self.inner.lock().unwrap();
+        let handle = inner.next_handle;
+        inner.next_handle += 1;
+        inner.objects.insert(handle, Box::into_raw(object) as usize);
+        handle
+    }
+
+    /// Return the address of the object behind the handle, or None if the handle is not live.
+    pub fn get(&self, handle: jlong) -> Option<*mut T> {
+        self.inner.lock().unwrap().objects.get(&handle).map(|&addr| addr as *mut T)
+    }
+
+    /// Return the number of live handles.
+    pub fn live_count(&self) -> usize {
+        self.inner.lock().unwrap().objects.len()
+    }
+
+    /// Release ownership of the object behind the handle, invalidating the handle.
+    pub fn remove(&self, handle: jlong) -> Option<Box<T>> {
+        let addr = self.inner.lock().unwrap().objects.remove(&handle)?;
+        // Safety: the address was produced by Box::into_raw in insert() and has just been removed
+        // from the table, so this is the only place reclaiming it.
+        Some(unsafe { Box::from_raw(addr as *mut T) })
+    }
+}
+
+impl<T> Drop for HandleTable<T> {
+    fn drop(&mut self) {
+        for (_, addr) in self.inner.get_mut().unwrap().objects.drain() {
+            // Safety: every address left in the table is still owned by it.
+            drop(unsafe { Box::from_raw(addr as *mut T) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let table = HandleTable::new();
+        let handle = table.insert(Box::new(42u32));
+        assert_ne!(handle, 0);
+        // Safety: the handle is live and nothing else accesses the object.
+        assert_eq!(unsafe { *table.get(handle).unwrap() }, 42);
+        assert_eq!(*table.remove(handle).unwrap(), 42);
+        assert!(table.get(handle).is_none());
+        assert!(table.remove(handle).is_none());
+    }
+
+    #[test]
+    fn test_handles_not_reused() {
+        let table = HandleTable::new();
+        let first = table.insert(Box::new(1u32));
+        table.remove(first);
+        let second = table.insert(Box::new(2u32));
+        assert_ne!(first, second);
+        assert!(table.get(first).is_none());
+    }
+
+    #[test]
+    fn test_no_leak_after_insert_remove_cycles() {
+        let table = HandleTable::new();
+        for i in 0..10u32 {
+            let handle = table.insert(Box::new(i));
+            assert_eq!(table.live_count(), 1);
+            table.remove(handle);
+        }
+        assert_eq!(table.live_count(), 0);
+    }
+
+    #[test]
+    fn test_unknown_handle() {
+        let table: HandleTable<u32> = HandleTable::new();
+        assert!(table.get(0).is_none());
+        assert!(table.get(12345).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+//! Block stride governor following the device interactive state.
+//!
+//! Sessions opt in with the block stride length to use while the device is not interactive. When
+//! the device leaves the interactive state, the governor saves the current block stride of every
+//! opted in session and asks for the idle one to be applied; when it becomes interactive again,
+//! the saved block strides are restored. The governor only decides which block stride to apply,
+//! sending the app configs is left to the caller.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub struct IdleGovernor {
+    inner: Mutex<IdleGovernorInner>,
+}
+
+struct IdleGovernorInner {
+    interactive: bool,
+    /// Block stride length to apply while not interactive, for each opted in session.
+    idle_strides: BTreeMap<u32, u8>,
+    /// Block stride length to restore on wake, for each session currently throttled.
+    saved_strides: BTreeMap<u32, u8>,
+    /// Number of times a session was throttled, for metrics.
+    throttle_count: u64,
+}
+
+impl IdleGovernor {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(IdleGovernorInner {
+                interactive: true,
+                idle_strides: BTreeMap::new(),
+                saved_strides: BTreeMap::new(),
+                throttle_count: 0,
+            }),
+        }
+    }
+
+    /// Opt the session in, the idle block stride is applied on the next interactive to idle
+    /// transition.
+    pub fn opt_in(&self, session_id: u32, idle_stride: u8) {
+        self.inner.lock().unwrap().idle_strides.insert(session_id, idle_stride);
+    }
+
+    /// Opt the session out, returning the block stride to restore if it is currently throttled.
+    pub fn opt_out(&self, session_id: u32) -> Option<u8> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.idle_strides.remove(&session_id);
+        inner.saved_strides.remove(&session_id)
+    }
+
+    /// Update the interactive state and return the (session id, block stride) pairs to apply.
+    /// current_stride gives the block stride a session currently uses.
+    pub fn set_interactive(
+        &self,
+        interactive: bool,
+        current_stride: impl Fn(u32) -> u8,
+    ) -> Vec<(u32, u8)> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.interactive == interactive {
+            return Vec::new();
+        }
+        inner.interactive = interactive;
+        if interactive {
+            return std::mem::take(&mut inner.saved_strides).into_iter().collect();
+        }
+        let changes: Vec<(u32, u8)> =
+            inner.idle_strides.iter().map(|(session_id, stride)| (*session_id, *stride)).collect();
+        for (session_id, _) in &changes {
+            inner.saved_strides.insert(*session_id, current_stride(*session_id));
+        }
+        inner.throttle_count += changes.len() as u64;
+        changes
+    }
+
+    /// Drop all state of a session that went away.
+    pub fn forget(&self, session_id: u32) {
+        self.opt_out(session_id);
+    }
+
+    pub fn throttle_count(&self) -> u64 {
+        self.inner.lock().unwrap().throttle_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_and_wake() {
+        let governor = IdleGovernor::new();
+        governor.opt_in(1, 4);
+        governor.opt_in(2, 9);
+
+        assert!(governor.set_interactive(true, |_| 0).is_empty());
+        assert_eq!(governor.set_interactive(false, |s| s as u8), vec![(1, 4), (2, 9)]);
+        assert!(governor.set_interactive(false, |_| 0).is_empty());
+        assert_eq!(governor.set_interactive(true, |_| 0), vec![(1, 1), (2, 2)]);
+        assert!(governor.set_interactive(true, |_| 0).is_empty());
+        assert_eq!(governor.throttle_count(), 2);
+    }
+
+    #[test]
+    fn test_opt_out() {
+        let governor = IdleGovernor::new();
+        governor.opt_in(1, 4);
+        assert_eq!(governor.opt_out(1), None);
+
+        governor.opt_in(1, 4);
+        governor.set_interactive(false, |_| 0);
+        assert_eq!(governor.opt_out(1), Some(0));
+        assert!(governor.set_interactive(true, |_| 0).is_empty());
+    }
+}
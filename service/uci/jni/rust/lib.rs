@@ -1,15 +1,29 @@
 //! jni for uwb native stack
+mod app_config_cache;
+mod clock;
+mod handle_table;
+mod idle_governor;
+mod native_stack;
+mod profile_store;
+mod session_config_blob;
+mod uci_metrics;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
 use jni::objects::{JObject, JValue};
 use jni::sys::{
-    jarray, jboolean, jbyte, jbyteArray, jint, jintArray, jlong, jobject, jshort, jshortArray,
-    jsize,
+    jarray, jboolean, jbyte, jbyteArray, jfloat, jint, jintArray, jlong, jobject, jshort,
+    jshortArray, jsize, jstring,
 };
 use jni::JNIEnv;
+use lazy_static::lazy_static;
 use log::{error, info};
-use num_traits::ToPrimitive;
+use num_traits::{FromPrimitive, ToPrimitive};
 use uwb_uci_packets::{
-    GetCapsInfoRspPacket, Packet, SessionGetAppConfigRspPacket, SessionSetAppConfigRspPacket,
-    StatusCode, UciResponseChild, UciResponsePacket, UciVendor_9_ResponseChild,
+    AppConfigTlv, AppConfigTlvType, CapTlv, CapTlvType, GetCapsInfoRspPacket,
+    GetDeviceInfoRspPacket, Packet, SessionGetAppConfigRspPacket, SessionSetAppConfigRspPacket,
+    SessionState, StatusCode, UciResponseChild, UciResponsePacket, UciVendor_9_ResponseChild,
     UciVendor_A_ResponseChild, UciVendor_B_ResponseChild, UciVendor_E_ResponseChild,
     UciVendor_F_ResponseChild,
 };
@@ -17,6 +31,42 @@ use uwb_uci_rust::error::UwbErr;
 use uwb_uci_rust::event_manager::EventManagerImpl as EventManager;
 use uwb_uci_rust::uci::{uci_hrcv::UciResponse, Dispatcher, DispatcherImpl, JNICommand};
 
+use crate::clock::{Clock, MonotonicClock};
+use crate::handle_table::HandleTable;
+use crate::idle_governor::IdleGovernor;
+use crate::native_stack::{NativeStack, StackCaches};
+use crate::profile_store::ProfileStore;
+use crate::session_config_blob::SessionConfigBlob;
+use crate::uci_metrics::UciMetrics;
+
+/// Maximum number of concurrent sessions reported to Java.
+const MAX_SESSION_NUMBER: jint = 5;
+
+/// Interval between the first two session state queries while waiting for a session state. It
+/// doubles after every query, up to SESSION_STATE_POLL_MAX_INTERVAL.
+const SESSION_STATE_POLL_INITIAL_INTERVAL: Duration = Duration::from_millis(50);
+const SESSION_STATE_POLL_MAX_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest wait for a session state, matching NativeUwbManager.MAX_WAIT_FOR_SESSION_STATE_MS.
+const SESSION_STATE_WAIT_MAX_TIMEOUT: Duration = Duration::from_secs(10);
+/// Returned to Java when the session did not reach the target state in time, matching
+/// NativeUwbManager.SESSION_STATE_WAIT_TIMEOUT.
+const SESSION_STATE_WAIT_TIMEOUT: jbyte = -2;
+
+/// Bitmap of PolicyOperation values the Java layer currently allows, everything by default.
+static COMMAND_POLICY: AtomicU32 = AtomicU32::new(u32::MAX);
+
+lazy_static! {
+    /// Dispatchers and their caches handed out to Java, keyed by the handle stored in
+    /// mDispatcherPointer.
+    static ref DISPATCHERS: HandleTable<NativeStack<DispatcherImpl>> = HandleTable::new();
+    /// Provisioned session profiles stored by Java.
+    static ref PROFILES: ProfileStore = ProfileStore::new();
+    /// Block stride governor following the interactive state reported by Java.
+    static ref IDLE_GOVERNOR: IdleGovernor = IdleGovernor::new();
+    /// Latency and failures of the UCI commands sent through any dispatcher.
+    static ref UCI_METRICS: UciMetrics = UciMetrics::new();
+}
+
 trait Context<'a> {
     fn convert_byte_array(&self, array: jbyteArray) -> Result<Vec<u8>, jni::errors::Error>;
     fn get_array_length(&self, array: jarray) -> Result<jsize, jni::errors::Error>;
@@ -33,6 +83,12 @@ trait Context<'a> {
         buf: &mut [jint],
     ) -> Result<(), jni::errors::Error>;
     fn get_dispatcher(&self) -> Result<&'a mut dyn Dispatcher, UwbErr>;
+    fn get_caches(&self) -> Result<&StackCaches, UwbErr>;
+    /// Run f while holding NativeUwbManager.mGetSessionStatusFnLock, like getSessionState does.
+    fn with_session_status_lock(
+        &self,
+        f: &mut dyn FnMut() -> Result<jbyte, UwbErr>,
+    ) -> Result<jbyte, UwbErr>;
 }
 
 struct JniContext<'a> {
@@ -44,6 +100,20 @@ impl<'a> JniContext<'a> {
     fn new(env: JNIEnv<'a>, obj: JObject<'a>) -> Self {
         Self { env, obj }
     }
+
+    fn get_native_stack(&self) -> Result<*mut NativeStack<DispatcherImpl>, UwbErr> {
+        let dispatcher_handle_value = self.env.get_field(self.obj, "mDispatcherPointer", "J")?;
+        let dispatcher_handle = dispatcher_handle_value.j()?;
+        if dispatcher_handle == 0i64 {
+            error!("The dispatcher is not initialized.");
+            return Err(UwbErr::NoneDispatcher);
+        }
+        // Entry points reject stale handles with ensure_live_dispatcher before getting here.
+        DISPATCHERS.get(dispatcher_handle).ok_or_else(|| {
+            error!("The dispatcher handle {} is not live.", dispatcher_handle);
+            UwbErr::NoneDispatcher
+        })
+    }
 }
 
 impl<'a> Context<'a> for JniContext<'a> {
@@ -70,16 +140,24 @@ impl<'a> Context<'a> for JniContext<'a> {
         self.env.get_int_array_region(array, start, buf)
     }
     fn get_dispatcher(&self) -> Result<&'a mut dyn Dispatcher, UwbErr> {
-        let dispatcher_ptr_value = self.env.get_field(self.obj, "mDispatcherPointer", "J")?;
-        let dispatcher_ptr = dispatcher_ptr_value.j()?;
-        if dispatcher_ptr == 0i64 {
-            error!("The dispatcher is not initialized.");
-            return Err(UwbErr::NoneDispatcher);
-        }
-        // Safety: dispatcher pointer must not be a null pointer and it must point to a valid dispatcher object.
-        // This can be ensured because the dispatcher is created in an earlier stage and
-        // won't be deleted before calling doDeinitialize.
-        unsafe { Ok(&mut *(dispatcher_ptr as *mut DispatcherImpl)) }
+        let native_stack = self.get_native_stack()?;
+        // Safety: the handle table only returns addresses of dispatchers it still owns.
+        // The dispatcher won't be removed from the table before calling doDeinitialize.
+        unsafe { Ok(&mut (*native_stack).dispatcher) }
+    }
+    fn get_caches(&self) -> Result<&StackCaches, UwbErr> {
+        let native_stack = self.get_native_stack()?;
+        // Safety: as for get_dispatcher, the caches are only borrowed through shared references.
+        unsafe { Ok(&(*native_stack).caches) }
+    }
+    fn with_session_status_lock(
+        &self,
+        f: &mut dyn FnMut() -> Result<jbyte, UwbErr>,
+    ) -> Result<jbyte, UwbErr> {
+        let lock =
+            self.env.get_field(self.obj, "mGetSessionStatusFnLock", "Ljava/lang/Object;")?.l()?;
+        let _guard = self.env.lock_obj(lock)?;
+        f()
     }
 }
 
@@ -106,7 +184,7 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     _obj: JObject,
 ) -> jint {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetMaxSessionNumber: enter");
-    5
+    MAX_SESSION_NUMBER
 }
 
 /// Turn on UWB. initialize the GKI module and HAL module for UWB device.
@@ -116,6 +194,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeDo
     obj: JObject,
 ) -> jboolean {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeDoInitialize: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return false as jboolean;
+    }
     boolean_result_helper(do_initialize(&JniContext::new(env, obj)), "DoInitialize")
 }
 
@@ -126,6 +207,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeDo
     obj: JObject,
 ) -> jboolean {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeDoDeinitialize: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return false as jboolean;
+    }
     boolean_result_helper(do_deinitialize(&JniContext::new(env, obj)), "DoDeinitialize")
 }
 
@@ -149,6 +233,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeDe
     reset_config: jbyte,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeDeviceReset: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::DeviceControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(reset_device(&JniContext::new(env, obj), reset_config as u8), "ResetDevice")
 }
 
@@ -159,12 +248,27 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
     obj: JObject,
     session_id: jint,
     session_type: jbyte,
-) -> jbyte {
+) -> jobject {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSessionInit: enter");
-    byte_result_helper(
-        session_init(&JniContext::new(env, obj), session_id as u32, session_type as u8),
-        "SessionInit",
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return *JObject::null();
+    }
+    let (result, uwbs_active_sessions) =
+        session_init_with_count(&JniContext::new(env, obj), session_id as u32, session_type as u8);
+    let uwb_session_init_status_class =
+        env.find_class("com/android/server/uwb/data/UwbSessionInitStatus").unwrap();
+    *env.new_object(
+        uwb_session_init_status_class,
+        "(BII)V",
+        &[
+            JValue::Byte(byte_result_helper(result, "SessionInit")),
+            JValue::Int(uwbs_active_sessions.map_or(-1, |count| count as i32)),
+            JValue::Int(MAX_SESSION_NUMBER),
+        ],
     )
+    .unwrap()
 }
 
 /// deinit the session
@@ -175,6 +279,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
     session_id: jint,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSessionDeInit: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(
         session_deinit(&JniContext::new(env, obj), session_id as u32),
         "SessionDeInit",
@@ -188,6 +297,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     obj: JObject,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetSessionCount: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     match get_session_count(&JniContext::new(env, obj)) {
         Ok(count) => count,
         Err(e) => {
@@ -205,6 +317,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeRa
     session_id: jint,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeRangingStart: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(ranging_start(&JniContext::new(env, obj), session_id as u32), "RangingStart")
 }
 
@@ -216,6 +333,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeRa
     session_id: jint,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeRangingStop: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(ranging_stop(&JniContext::new(env, obj), session_id as u32), "RangingStop")
 }
 
@@ -227,6 +349,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     session_id: jint,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetSessionState: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     match get_session_state(&JniContext::new(env, obj), session_id as u32) {
         Ok(state) => state,
         Err(e) => {
@@ -236,6 +361,35 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     }
 }
 
+/// wait until the session reaches the target state or the timeout expires
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeWaitForSessionState(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    target_state: jbyte,
+    timeout_ms: jint,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeWaitForSessionState: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
+    match wait_for_session_state(
+        &JniContext::new(env, obj),
+        session_id as u32,
+        target_state,
+        Duration::from_millis(timeout_ms.max(0) as u64),
+        &MonotonicClock,
+    ) {
+        Ok(true) => target_state,
+        Ok(false) => SESSION_STATE_WAIT_TIMEOUT,
+        Err(e) => {
+            error!("WaitForSessionState failed with {:?}", e);
+            -1
+        }
+    }
+}
+
 /// set app configurations
 #[no_mangle]
 pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetAppConfigurations(
@@ -247,6 +401,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
     app_config_params: jbyteArray,
 ) -> jbyteArray {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetAppConfigurations: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return *JObject::null();
+    }
     match set_app_configurations(
         &JniContext::new(env, obj),
         session_id as u32,
@@ -290,25 +449,26 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     no_of_params: jint,
     app_config_param_len: jint,
     app_config_params: jbyteArray,
+    cached: jboolean,
 ) -> jbyteArray {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetAppConfigurations: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
     match get_app_configurations(
         &JniContext::new(env, obj),
         session_id as u32,
         no_of_params as u32,
         app_config_param_len as u32,
         app_config_params,
+        cached != 0,
     ) {
         Ok(data) => {
             let uwb_tlv_info_class =
                 env.find_class("com/android/server/uwb/data/UwbTlvData").unwrap();
-            let mut buf: Vec<u8> = Vec::new();
-            for tlv in data.get_tlvs() {
-                buf.push(tlv.cfg_id as u8);
-                buf.push(tlv.v.len() as u8);
-                buf.extend(&tlv.v);
-            }
-            let tlv_jbytearray = env.byte_array_from_slice(&buf).unwrap();
+            let tlvs: Vec<(u8, Vec<u8>)> =
+                data.get_tlvs().iter().map(|tlv| (tlv.cfg_id as u8, tlv.v.clone())).collect();
+            let tlv_jbytearray = env.byte_array_from_slice(&encode_app_config_tlvs(&tlvs)).unwrap();
             let uwb_tlv_info_object = env.new_object(
                 uwb_tlv_info_class,
                 "(II[B)V",
@@ -334,6 +494,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     obj: JObject,
 ) -> jbyteArray {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetCapsInfo: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
     match get_caps_info(&JniContext::new(env, obj)) {
         Ok(data) => {
             let uwb_tlv_info_class =
@@ -375,6 +538,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeCo
     sub_session_ids: jintArray,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeControllerMulticastListUpdate: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(
         multicast_list_update(
             &JniContext::new(env, obj),
@@ -388,6 +556,252 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeCo
     )
 }
 
+/// configure range data notifications
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeConfigureRangeDataNtf(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    range_data_ntf_config: jint,
+    proximity_near: jint,
+    proximity_far: jint,
+    azimuth_lower: jfloat,
+    azimuth_upper: jfloat,
+    elevation_lower: jfloat,
+    elevation_upper: jfloat,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeConfigureRangeDataNtf: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
+    byte_result_helper(
+        configure_range_data_ntf(
+            &JniContext::new(env, obj),
+            session_id as u32,
+            range_data_ntf_config,
+            proximity_near,
+            proximity_far,
+            (azimuth_lower, azimuth_upper),
+            (elevation_lower, elevation_upper),
+        ),
+        "ConfigureRangeDataNtf",
+    )
+}
+
+/// reconfigure the session with only the app configs that changed
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeReconfigureSession(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    app_config_params: jbyteArray,
+) -> jobject {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeReconfigureSession: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return *JObject::null();
+    }
+    match reconfigure_session(&JniContext::new(env, obj), session_id as u32, app_config_params) {
+        Ok((status, delta)) => {
+            let uwb_tlv_info_class =
+                env.find_class("com/android/server/uwb/data/UwbTlvData").unwrap();
+            let tlv_jbytearray =
+                env.byte_array_from_slice(&encode_app_config_tlvs(&delta)).unwrap();
+            let uwb_tlv_info_object = env.new_object(
+                uwb_tlv_info_class,
+                "(II[B)V",
+                &[
+                    JValue::Int(status.to_i32().unwrap()),
+                    JValue::Int(delta.len().to_i32().unwrap()),
+                    JValue::Object(JObject::from(tlv_jbytearray)),
+                ],
+            );
+            *uwb_tlv_info_object.unwrap()
+        }
+        Err(e) => {
+            error!("ReconfigureSession failed with: {:?}", e);
+            *JObject::null()
+        }
+    }
+}
+
+/// export the configuration of the session as a portable blob
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeExportSessionConfig(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+) -> jbyteArray {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeExportSessionConfig: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
+    match export_session_config(&JniContext::new(env, obj), session_id as u32) {
+        Ok(blob) => env.byte_array_from_slice(&blob).unwrap(),
+        Err(e) => {
+            error!("ExportSessionConfig failed with: {:?}", e);
+            *JObject::null()
+        }
+    }
+}
+
+/// open a session configured from an exported blob
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeImportSessionConfig(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    blob: jbyteArray,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeImportSessionConfig: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
+    byte_result_helper(
+        import_session_config(&JniContext::new(env, obj), session_id as u32, blob),
+        "ImportSessionConfig",
+    )
+}
+
+/// list the ids of the app configs set on the session and whether the chip confirmed them
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeListConfiguredAppConfigIds(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+) -> jobject {
+    info!(
+        "Java_com_android_server_uwb_jni_NativeUwbManager_nativeListConfiguredAppConfigIds: enter"
+    );
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
+    let ids = match list_configured_app_config_ids(&JniContext::new(env, obj), session_id as u32) {
+        Ok(ids) => ids,
+        Err(e) => {
+            error!("ListConfiguredAppConfigIds failed with: {:?}", e);
+            return *JObject::null();
+        }
+    };
+    let uwb_config_status_class =
+        env.find_class("com/android/server/uwb/data/UwbConfigStatusData").unwrap();
+    let mut buf: Vec<u8> = Vec::new();
+    for (cfg_id, status) in &ids {
+        buf.push(*cfg_id);
+        buf.push(*status);
+    }
+    let cfg_jbytearray = env.byte_array_from_slice(&buf).unwrap();
+    let uwb_config_status_object = env.new_object(
+        uwb_config_status_class,
+        "(II[B)V",
+        &[
+            JValue::Int(StatusCode::UciStatusOk.to_i32().unwrap()),
+            JValue::Int(ids.len().to_i32().unwrap()),
+            JValue::Object(JObject::from(cfg_jbytearray)),
+        ],
+    );
+    *uwb_config_status_object.unwrap()
+}
+
+/// opt the session in or out of the idle block stride governor
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetIdleBlockStride(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    idle_block_stride: jint,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetIdleBlockStride: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
+    byte_result_helper(
+        set_idle_block_stride(&JniContext::new(env, obj), session_id as u32, idle_block_stride),
+        "SetIdleBlockStride",
+    )
+}
+
+/// update the device interactive state used by the idle block stride governor
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetDeviceInteractive(
+    env: JNIEnv,
+    obj: JObject,
+    interactive: jboolean,
+) {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetDeviceInteractive: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return;
+    }
+    set_device_interactive(&JniContext::new(env, obj), interactive != 0);
+}
+
+/// store a provisioned session profile
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeStoreProfile(
+    env: JNIEnv,
+    obj: JObject,
+    profile_id: jint,
+    blob: jbyteArray,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeStoreProfile: enter");
+    byte_result_helper(
+        store_profile(&JniContext::new(env, obj), profile_id as u32, blob),
+        "StoreProfile",
+    )
+}
+
+/// list the ids of the provisioned session profiles
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeListProfiles(
+    env: JNIEnv,
+    _obj: JObject,
+) -> jintArray {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeListProfiles: enter");
+    let ids: Vec<jint> = PROFILES.ids().into_iter().map(|id| id as jint).collect();
+    let array = env.new_int_array(ids.len() as jsize).unwrap();
+    env.set_int_array_region(array, 0, &ids).unwrap();
+    array
+}
+
+/// remove a provisioned session profile
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeRemoveProfile(
+    _env: JNIEnv,
+    _obj: JObject,
+    profile_id: jint,
+) -> jboolean {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeRemoveProfile: enter");
+    PROFILES.remove(profile_id as u32) as jboolean
+}
+
+/// open a session configured from a provisioned profile
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeOpenSessionWithProfile(
+    env: JNIEnv,
+    obj: JObject,
+    session_id: jint,
+    profile_id: jint,
+) -> jbyte {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeOpenSessionWithProfile: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::SessionControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
+    byte_result_helper(
+        open_session_with_profile(&JniContext::new(env, obj), session_id as u32, profile_id as u32),
+        "OpenSessionWithProfile",
+    )
+}
+
 /// set country code
 #[no_mangle]
 pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetCountryCode(
@@ -396,6 +810,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
     country_code: jbyteArray,
 ) -> jbyte {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeSetCountryCode: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::DeviceControl)
+    {
+        return StatusCode::UciStatusFailed.to_i8().unwrap();
+    }
     byte_result_helper(set_country_code(&JniContext::new(env, obj), country_code), "SetCountryCode")
 }
 
@@ -409,6 +828,11 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
     payload: jbyteArray,
 ) -> jobject {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeRawVendor: enter");
+    if !ensure_live_dispatcher(&env, obj)
+        || !ensure_allowed_by_policy(&env, PolicyOperation::RawVendorCommand)
+    {
+        return *JObject::null();
+    }
     let uwb_vendor_uci_response_class =
         env.find_class("com/android/server/uwb/data/UwbVendorUciResponse").unwrap();
     match send_raw_vendor_cmd(
@@ -437,7 +861,7 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeSe
                 uwb_vendor_uci_response_class,
                 "(BII[B)V",
                 &[
-                    JValue::Byte(StatusCode::UciStatusFailed.to_i8().unwrap()),
+                    JValue::Byte(status_code_from_err(&e)),
                     JValue::Int(-1),
                     JValue::Int(-1),
                     JValue::Object(JObject::null()),
@@ -455,6 +879,9 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     obj: JObject,
 ) -> jobject {
     info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetPowerStats: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
     let uwb_power_stats_class =
         env.find_class("com/android/server/uwb/info/UwbPowerStats").unwrap();
     match get_power_stats(&JniContext::new(env, obj)) {
@@ -469,6 +896,112 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGe
     }
 }
 
+/// get the device info
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetDeviceInfo(
+    env: JNIEnv,
+    obj: JObject,
+    refresh: jboolean,
+) -> jobject {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetDeviceInfo: enter");
+    if !ensure_live_dispatcher(&env, obj) {
+        return *JObject::null();
+    }
+    match get_device_info(&JniContext::new(env, obj), refresh != 0) {
+        Ok(data) => {
+            let uwb_device_info_class =
+                env.find_class("com/android/server/uwb/data/UwbDeviceInfoResponse").unwrap();
+            let vendor_spec_info = env.byte_array_from_slice(data.get_vendor_spec_info()).unwrap();
+            *env.new_object(
+                uwb_device_info_class,
+                "(BIIII[B)V",
+                &[
+                    JValue::Byte(data.get_status().to_i8().unwrap()),
+                    JValue::Int(data.get_uci_version().into()),
+                    JValue::Int(data.get_mac_version().into()),
+                    JValue::Int(data.get_phy_version().into()),
+                    JValue::Int(data.get_uci_test_version().into()),
+                    JValue::Object(JObject::from(vendor_spec_info)),
+                ],
+            )
+            .unwrap()
+        }
+        Err(e) => {
+            error!("GetDeviceInfo failed with: {:?}", e);
+            *JObject::null()
+        }
+    }
+}
+
+/// get the native stack info
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetNativeStackInfo(
+    env: JNIEnv,
+    obj: JObject,
+) -> jstring {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetNativeStackInfo: enter");
+    let context = JniContext::new(env, obj);
+    match env.new_string(native_stack_info(context.get_caches().ok())) {
+        Ok(info) => info.into_inner(),
+        Err(e) => {
+            error!("GetNativeStackInfo failed with: {:?}", e);
+            *JObject::null()
+        }
+    }
+}
+
+/// get the per-command UCI metrics
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetUciMetrics(
+    env: JNIEnv,
+    _obj: JObject,
+) -> jstring {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeGetUciMetrics: enter");
+    match env.new_string(UCI_METRICS.summary()) {
+        Ok(metrics) => metrics.into_inner(),
+        Err(e) => {
+            error!("GetUciMetrics failed with: {:?}", e);
+            *JObject::null()
+        }
+    }
+}
+
+/// update the command policy
+#[no_mangle]
+pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeUpdatePolicy(
+    _env: JNIEnv,
+    _obj: JObject,
+    policy: jint,
+) {
+    info!("Java_com_android_server_uwb_jni_NativeUwbManager_nativeUpdatePolicy: enter");
+    COMMAND_POLICY.store(policy as u32, Ordering::SeqCst);
+}
+
+/// Describe the native stack, including the caches of the dispatcher if it is initialized. The
+/// version comes from cargo_pkg_version and the flags from the features set in Android.bp.
+fn native_stack_info(caches: Option<&StackCaches>) -> String {
+    format!(
+        concat!(
+            "crate: {} {}\n",
+            "stack: uwb_uci_rust dispatcher\n",
+            "data_transfer: {}\n",
+            "radar: {}\n",
+            "mock_chip: {}\n",
+            "live_dispatcher_handles: {}\n",
+            "app_config_cached_sessions: {}\n",
+            "idle_governor_throttle_count: {}\n",
+        ),
+        module_path!(),
+        env!("CARGO_PKG_VERSION"),
+        cfg!(feature = "data_transfer"),
+        cfg!(feature = "radar"),
+        cfg!(feature = "mock_chip"),
+        DISPATCHERS.live_count(),
+        caches.map_or(0, |caches| caches.app_configs.session_count()),
+        IDLE_GOVERNOR.throttle_count(),
+    )
+}
+
 fn boolean_result_helper(result: Result<(), UwbErr>, function_name: &str) -> jboolean {
     match result {
         Ok(()) => true as jboolean,
@@ -484,14 +1017,48 @@ fn byte_result_helper(result: Result<(), UwbErr>, function_name: &str) -> jbyte
         Ok(()) => StatusCode::UciStatusOk.to_i8().unwrap(),
         Err(err) => {
             error!("{} failed with: {:?}", function_name, err);
-            match err {
-                UwbErr::StatusCode(status_code) => status_code
-                    .to_i8()
-                    .unwrap_or_else(|| StatusCode::UciStatusFailed.to_i8().unwrap()),
-                _ => StatusCode::UciStatusFailed.to_i8().unwrap(),
-            }
+            status_code_from_err(&err)
+        }
+    }
+}
+
+fn status_code_from_err(err: &UwbErr) -> jbyte {
+    match err {
+        UwbErr::StatusCode(status_code) => {
+            status_code.to_i8().unwrap_or_else(|| StatusCode::UciStatusFailed.to_i8().unwrap())
         }
+        _ => StatusCode::UciStatusFailed.to_i8().unwrap(),
+    }
+}
+
+/// Classes of commands that the policy pushed from Java can disallow.
+#[derive(Clone, Copy, Debug)]
+enum PolicyOperation {
+    DeviceControl = 1 << 0,
+    SessionControl = 1 << 1,
+    RawVendorCommand = 1 << 2,
+}
+
+fn is_allowed_by_policy(policy: u32, operation: PolicyOperation) -> bool {
+    policy & operation as u32 != 0
+}
+
+/// Check the command policy before an entry point sends a command of the given class. A disallowed
+/// command raises a SecurityException in Java, so that it is not mistaken for a rejection by the
+/// UWBS, and returns false; the entry point must then return right away without any other JNI
+/// call.
+fn ensure_allowed_by_policy(env: &JNIEnv, operation: PolicyOperation) -> bool {
+    if is_allowed_by_policy(COMMAND_POLICY.load(Ordering::SeqCst), operation) {
+        return true;
     }
+    error!("{:?} is disallowed by the command policy", operation);
+    if let Err(err) = env.throw_new(
+        "java/lang/SecurityException",
+        format!("UWB {:?} commands are disallowed by the command policy", operation),
+    ) {
+        error!("Failed to throw SecurityException with: {:?}", err);
+    }
+    false
 }
 
 fn do_initialize<'a, T: Context<'a>>(context: &T) -> Result<(), UwbErr> {
@@ -508,6 +1075,8 @@ fn do_initialize<'a, T: Context<'a>>(context: &T) -> Result<(), UwbErr> {
             return Err(UwbErr::failed());
         }
     }
+    // Caps are not fetched here so that initialization does not wait for them. get_caps_info
+    // fetches them on first use and caches them until the UWBS is reset.
     Ok(())
 }
 
@@ -515,6 +1084,8 @@ fn do_deinitialize<'a, T: Context<'a>>(context: &T) -> Result<(), UwbErr> {
     let dispatcher = context.get_dispatcher()?;
     dispatcher.send_jni_command(JNICommand::Disable(true))?;
     dispatcher.wait_for_exit()?;
+    // The UWBS is powered off, none of its sessions survive.
+    context.get_caches()?.clear();
     Ok(())
 }
 
@@ -550,33 +1121,117 @@ fn get_specification_info<'a, T: Context<'a>>(context: &T) -> Result<[JValue<'a>
     }
 }
 
-fn session_init<'a, T: Context<'a>>(
+fn get_device_info<'a, T: Context<'a>>(
     context: &T,
-    session_id: u32,
-    session_type: u8,
-) -> Result<(), UwbErr> {
+    refresh: bool,
+) -> Result<GetDeviceInfoRspPacket, UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher
-        .block_on_jni_command(JNICommand::UciSessionInit(session_id, session_type))?
-    {
-        UciResponse::SessionInitRsp(data) => data,
-        _ => return Err(UwbErr::failed()),
-    };
+    if refresh || dispatcher.get_device_info().is_none() {
+        match uwa_get_device_info(dispatcher)? {
+            UciResponse::GetDeviceInfoRsp(device_info) => {
+                // Keep the cached device info rather than replace it with a failed response.
+                status_code_to_res(device_info.get_status())?;
+                dispatcher.set_device_info(Some(device_info))
+            }
+            _ => return Err(UwbErr::failed()),
+        }
+    }
+    dispatcher.get_device_info().clone().ok_or_else(UwbErr::failed)
+}
+
+fn validate_session_type(session_type: u8) -> Result<(), UwbErr> {
+    match session_type {
+        // FiRa ranging, ranging and in-band data, data transfer and the hybrid phase types.
+        0x00..=0x05
+        // FiRa 2.0 hybrid UWB scheduling (HUS) primary session.
+        | 0x9F
+        // CCC and vendor specific.
+        | 0xA0..=0xCF
+        // Device test mode.
+        | 0xD0
+        // Vendor specific.
+        | 0xE0..=0xFF => Ok(()),
+        _ => {
+            error!("Invalid session type {:#04x}", session_type);
+            Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+        }
+    }
+}
+
+fn session_init<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    session_type: u8,
+) -> Result<(), UwbErr> {
+    session_init_with_count(context, session_id, session_type).0
+}
+
+/// Initialize the session. If the UWBS rejects it because it ran out of sessions, also return the
+/// number of sessions the UWBS reports as active, i.e. the limit it enforces, when available.
+fn session_init_with_count<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    session_type: u8,
+) -> (Result<(), UwbErr>, Option<u8>) {
+    match send_session_init(context, session_id, session_type) {
+        Err(UwbErr::StatusCode(StatusCode::UciStatusMaxSessionsExceeded)) => {
+            let uwbs_active_sessions = match get_session_count(context) {
+                Ok(count) => Some(count as u8),
+                Err(e) => {
+                    error!("GetSessionCount after SessionInit {} failed: {:?}", session_id, e);
+                    None
+                }
+            };
+            error!(
+                "SessionInit {} rejected by UWBS with {:?} sessions active (JNI session limit {})",
+                session_id, uwbs_active_sessions, MAX_SESSION_NUMBER
+            );
+            UCI_METRICS.record_max_sessions_exceeded(
+                uwbs_active_sessions.map_or(-1, |count| count as i32),
+                MAX_SESSION_NUMBER,
+            );
+            (
+                Err(UwbErr::StatusCode(StatusCode::UciStatusMaxSessionsExceeded)),
+                uwbs_active_sessions,
+            )
+        }
+        result => (result, None),
+    }
+}
+
+fn send_session_init<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    session_type: u8,
+) -> Result<(), UwbErr> {
+    validate_session_type(session_type)?;
+    let dispatcher = context.get_dispatcher()?;
+    let res =
+        match block_on_command(dispatcher, JNICommand::UciSessionInit(session_id, session_type))? {
+            UciResponse::SessionInitRsp(data) => data,
+            _ => return Err(UwbErr::failed()),
+        };
+    if res.get_status() == StatusCode::UciStatusOk {
+        context.get_caches()?.app_configs.init_session(session_id, session_type);
+    }
     status_code_to_res(res.get_status())
 }
 
 fn session_deinit<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<(), UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciSessionDeinit(session_id))? {
+    let res = match block_on_command(dispatcher, JNICommand::UciSessionDeinit(session_id))? {
         UciResponse::SessionDeinitRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
-    status_code_to_res(res.get_status())
+    status_code_to_res(res.get_status())?;
+    context.get_caches()?.app_configs.remove(session_id);
+    IDLE_GOVERNOR.forget(session_id);
+    Ok(())
 }
 
 fn get_session_count<'a, T: Context<'a>>(context: &T) -> Result<jbyte, UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciSessionGetCount)? {
+    match block_on_command(dispatcher, JNICommand::UciSessionGetCount)? {
         UciResponse::SessionGetCountRsp(rsp) => match status_code_to_res(rsp.get_status()) {
             Ok(()) => Ok(rsp.get_session_count() as jbyte),
             Err(err) => Err(err),
@@ -587,7 +1242,7 @@ fn get_session_count<'a, T: Context<'a>>(context: &T) -> Result<jbyte, UwbErr> {
 
 fn ranging_start<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<(), UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciStartRange(session_id))? {
+    let res = match block_on_command(dispatcher, JNICommand::UciStartRange(session_id))? {
         UciResponse::RangeStartRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
@@ -596,7 +1251,7 @@ fn ranging_start<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<(),
 
 fn ranging_stop<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<(), UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciStopRange(session_id))? {
+    let res = match block_on_command(dispatcher, JNICommand::UciStopRange(session_id))? {
         UciResponse::RangeStopRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
@@ -605,12 +1260,43 @@ fn ranging_stop<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<(),
 
 fn get_session_state<'a, T: Context<'a>>(context: &T, session_id: u32) -> Result<jbyte, UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciGetSessionState(session_id))? {
-        UciResponse::SessionGetStateRsp(data) => Ok(data.get_session_state() as jbyte),
+    match block_on_command(dispatcher, JNICommand::UciGetSessionState(session_id))? {
+        UciResponse::SessionGetStateRsp(data) => {
+            status_code_to_res(data.get_status())?;
+            Ok(data.get_session_state() as jbyte)
+        }
         _ => Err(UwbErr::failed()),
     }
 }
 
+/// Query the session state, backing off between queries, until it is target_state or the timeout
+/// expires. The timeout is capped at SESSION_STATE_WAIT_MAX_TIMEOUT. Each query holds the Java
+/// session status lock, which is released while sleeping. Return whether the session reached
+/// target_state.
+fn wait_for_session_state<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    target_state: jbyte,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<bool, UwbErr> {
+    let deadline = clock.now() + timeout.min(SESSION_STATE_WAIT_MAX_TIMEOUT);
+    let mut interval = SESSION_STATE_POLL_INITIAL_INTERVAL;
+    loop {
+        let state =
+            context.with_session_status_lock(&mut || get_session_state(context, session_id))?;
+        if state == target_state {
+            return Ok(true);
+        }
+        let now = clock.now();
+        if now >= deadline {
+            return Ok(false);
+        }
+        clock.sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(SESSION_STATE_POLL_MAX_INTERVAL);
+    }
+}
+
 fn set_app_configurations<'a, T: Context<'a>>(
     context: &T,
     session_id: u32,
@@ -619,14 +1305,56 @@ fn set_app_configurations<'a, T: Context<'a>>(
     app_config_params: jintArray,
 ) -> Result<SessionSetAppConfigRspPacket, UwbErr> {
     let app_configs = context.convert_byte_array(app_config_params)?;
+    apply_app_configurations(context, session_id, no_of_params, app_config_param_len, app_configs)
+}
+
+/// Send the TLVs to the chip and, if all of them were accepted, record them as the session's
+/// current configs.
+fn apply_app_configurations<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    no_of_params: u32,
+    app_config_param_len: u32,
+    app_configs: Vec<u8>,
+) -> Result<SessionSetAppConfigRspPacket, UwbErr> {
+    let tlvs = parse_app_config_tlvs(&app_configs)?;
+    // Validate the configs the session ends up with, the request may only carry part of them.
+    let cached = context.get_caches()?.app_configs.get(session_id, &[]).unwrap_or_default();
+    let mut merged: Vec<(u8, &[u8])> =
+        cached.iter().map(|(cfg_id, value)| (*cfg_id, value.as_slice())).collect();
+    merged.extend(&tlvs);
+    validate_sts_configs(&merged)?;
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciSetAppConfig {
-        session_id,
-        no_of_params,
-        app_config_param_len,
-        app_configs,
-    })? {
-        UciResponse::SessionSetAppConfigRsp(data) => Ok(data),
+    match block_on_command(
+        dispatcher,
+        JNICommand::UciSetAppConfig {
+            session_id,
+            no_of_params,
+            app_config_param_len,
+            app_configs: app_configs.clone(),
+        },
+    )? {
+        UciResponse::SessionSetAppConfigRsp(data) => {
+            if data.get_status() == StatusCode::UciStatusOk {
+                context.get_caches()?.app_configs.update(session_id, &tlvs);
+            } else {
+                // Configs missing from cfg_status were not rejected individually, but not
+                // confirmed either.
+                let statuses: Vec<(u8, u8)> = tlvs
+                    .iter()
+                    .map(|(cfg_id, _)| {
+                        let status = data
+                            .get_cfg_status()
+                            .iter()
+                            .find(|cfg_status| cfg_status.cfg_id as u8 == *cfg_id)
+                            .map_or(data.get_status(), |cfg_status| cfg_status.status);
+                        (*cfg_id, status as u8)
+                    })
+                    .collect();
+                context.get_caches()?.app_configs.record_unconfirmed(session_id, &statuses);
+            }
+            Ok(data)
+        }
         _ => Err(UwbErr::failed()),
     }
 }
@@ -637,24 +1365,485 @@ fn get_app_configurations<'a, T: Context<'a>>(
     no_of_params: u32,
     app_config_param_len: u32,
     app_config_params: jintArray,
+    cached: bool,
 ) -> Result<SessionGetAppConfigRspPacket, UwbErr> {
     let app_configs = context.convert_byte_array(app_config_params)?;
+    if cached {
+        return get_cached_app_configurations(context, session_id, &app_configs);
+    }
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciGetAppConfig {
+    match block_on_command(
+        dispatcher,
+        JNICommand::UciGetAppConfig { session_id, no_of_params, app_config_param_len, app_configs },
+    )? {
+        UciResponse::SessionGetAppConfigRsp(data) => Ok(data),
+        _ => Err(UwbErr::failed()),
+    }
+}
+
+/// Answer a get app config request from the configs last applied to the session, without
+/// sending anything to the chip.
+fn get_cached_app_configurations<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    cfg_ids: &[u8],
+) -> Result<SessionGetAppConfigRspPacket, UwbErr> {
+    let configs = context.get_caches()?.app_configs.get(session_id, cfg_ids).ok_or_else(|| {
+        error!("No app config cached for session {}", session_id);
+        UwbErr::StatusCode(StatusCode::UciStatusSessionNotExist)
+    })?;
+    let tlvs = configs
+        .into_iter()
+        .filter_map(|(cfg_id, v)| {
+            AppConfigTlvType::from_u8(cfg_id).map(|cfg_id| AppConfigTlv { cfg_id, v })
+        })
+        .collect();
+    Ok(uwb_uci_packets::SessionGetAppConfigRspBuilder { status: StatusCode::UciStatusOk, tlvs }
+        .build())
+}
+
+/// Return the id of every app config set on the session, each with the status of its last update.
+fn list_configured_app_config_ids<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+) -> Result<Vec<(u8, u8)>, UwbErr> {
+    context.get_caches()?.app_configs.configured_ids(session_id).ok_or_else(|| {
+        error!("No app config cached for session {}", session_id);
+        UwbErr::StatusCode(StatusCode::UciStatusSessionNotExist)
+    })
+}
+
+fn encode_app_config_tlvs(tlvs: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    for (cfg_id, v) in tlvs {
+        buf.push(*cfg_id);
+        buf.push(v.len() as u8);
+        buf.extend(v);
+    }
+    buf
+}
+
+fn parse_app_config_tlvs(app_configs: &[u8]) -> Result<Vec<(u8, &[u8])>, UwbErr> {
+    let mut tlvs = Vec::new();
+    let mut rest = app_configs;
+    while !rest.is_empty() {
+        match rest {
+            [cfg_id, len, tail @ ..] if tail.len() >= *len as usize => {
+                let (value, next) = tail.split_at(*len as usize);
+                tlvs.push((*cfg_id, value));
+                rest = next;
+            }
+            _ => {
+                error!("Malformed app config TLV at offset {}", app_configs.len() - rest.len());
+                return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+            }
+        }
+    }
+    Ok(tlvs)
+}
+
+/// Check that the STS related configs are consistent, so that a bad combination is rejected here
+/// with a precise cause instead of failing opaquely on the chip. When a config appears more than
+/// once the last value wins, so the configs cached for a session can be followed by the requested
+/// ones. Key presence is only enforced when STS_CONFIG is set.
+fn validate_sts_configs(tlvs: &[(u8, &[u8])]) -> Result<(), UwbErr> {
+    let find = |cfg_id: AppConfigTlvType| {
+        tlvs.iter().rev().find(|(id, _)| *id == cfg_id as u8).map(|(_, value)| *value)
+    };
+    let invalid = |cause: &str| {
+        error!("Invalid STS configuration: {}", cause);
+        Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+    };
+
+    // Key length checks apply whenever the key is sent.
+    for (cfg_id, name, len) in [
+        (AppConfigTlvType::VendorId, "VENDOR_ID", 2),
+        (AppConfigTlvType::StaticStsIv, "STATIC_STS_IV", 6),
+        (AppConfigTlvType::SubSessionId, "SUB_SESSION_ID", 4),
+    ] {
+        if let Some(value) = find(cfg_id) {
+            if value.len() != len {
+                return invalid(&format!("{} is {} bytes, expected {}", name, value.len(), len));
+            }
+        }
+    }
+
+    match find(AppConfigTlvType::StsConfig) {
+        None => Ok(()),
+        // Static STS, derived from the vendor id and static STS IV.
+        Some([0x00]) => {
+            match (find(AppConfigTlvType::VendorId), find(AppConfigTlvType::StaticStsIv)) {
+                (Some(_), Some(_)) => Ok(()),
+                _ => invalid("static STS requires VENDOR_ID and STATIC_STS_IV"),
+            }
+        }
+        // Dynamic STS.
+        Some([0x01]) => Ok(()),
+        // Dynamic STS with responder specific sub-session key.
+        Some([0x02]) => match find(AppConfigTlvType::SubSessionId) {
+            Some(_) => Ok(()),
+            None => invalid("dynamic STS with individual keys requires SUB_SESSION_ID"),
+        },
+        Some(value) => invalid(&format!("unsupported STS_CONFIG {:02x?}", value)),
+    }
+}
+
+/// FiRa 2.0 RANGE_DATA_NTF_AOA_BOUND, unknown to AppConfigTlvType. Its value is the azimuth lower
+/// and upper bounds followed by the elevation lower and upper bounds.
+const RANGE_DATA_NTF_AOA_BOUND: u8 = 0x1D;
+
+/// Whether the range data ntf config, up to the FiRa 2.0 edge triggered ones, uses the AoA bound.
+fn uses_aoa_bound(range_data_ntf_config: u8) -> bool {
+    matches!(range_data_ntf_config, 0x03 | 0x04 | 0x06 | 0x07)
+}
+
+/// Whether (lower, upper) in degrees is an ordered range within [-limit, limit].
+fn is_valid_angle_range((lower, upper): (f32, f32), limit: f32) -> bool {
+    -limit <= lower && lower <= upper && upper <= limit
+}
+
+/// Encode an angle in degrees as the signed Q9.7 value of RANGE_DATA_NTF_AOA_BOUND.
+fn encode_aoa_bound_angle(degrees: f32) -> [u8; 2] {
+    ((degrees * 128.0).round() as i16).to_le_bytes()
+}
+
+/// Pack the range data ntf config and its bounds into TLVs. Angles are in degrees, the AoA bound
+/// is only sent with a config that uses it.
+fn range_data_ntf_tlvs(
+    range_data_ntf_config: i32,
+    proximity_near: i32,
+    proximity_far: i32,
+    azimuth: (f32, f32),
+    elevation: (f32, f32),
+) -> Result<Vec<(u8, Vec<u8>)>, UwbErr> {
+    let config: u8 = match range_data_ntf_config {
+        0..=7 => range_data_ntf_config as u8,
+        _ => {
+            error!("Invalid range data ntf config {}", range_data_ntf_config);
+            return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+        }
+    };
+    let (near, far): (u16, u16) =
+        match (u16::try_from(proximity_near), u16::try_from(proximity_far)) {
+            (Ok(near), Ok(far)) if near <= far => (near, far),
+            _ => {
+                error!("Invalid proximity bounds near: {} far: {}", proximity_near, proximity_far);
+                return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+            }
+        };
+    if !is_valid_angle_range(azimuth, 180.0) || !is_valid_angle_range(elevation, 90.0) {
+        error!("Invalid AoA bounds azimuth: {:?} elevation: {:?}", azimuth, elevation);
+        return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+    }
+    let mut tlvs = vec![
+        (AppConfigTlvType::RangeDataNtfConfig as u8, vec![config]),
+        (AppConfigTlvType::RangeDataNtfProximityNear as u8, near.to_le_bytes().to_vec()),
+        (AppConfigTlvType::RangeDataNtfProximityFar as u8, far.to_le_bytes().to_vec()),
+    ];
+    if uses_aoa_bound(config) {
+        let bound = [azimuth.0, azimuth.1, elevation.0, elevation.1]
+            .iter()
+            .flat_map(|degrees| encode_aoa_bound_angle(*degrees))
+            .collect();
+        tlvs.push((RANGE_DATA_NTF_AOA_BOUND, bound));
+    }
+    Ok(tlvs)
+}
+
+fn configure_range_data_ntf<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    range_data_ntf_config: i32,
+    proximity_near: i32,
+    proximity_far: i32,
+    azimuth: (f32, f32),
+    elevation: (f32, f32),
+) -> Result<(), UwbErr> {
+    let tlvs = range_data_ntf_tlvs(
+        range_data_ntf_config,
+        proximity_near,
+        proximity_far,
+        azimuth,
+        elevation,
+    )?;
+    let app_configs = encode_app_config_tlvs(&tlvs);
+    let res = apply_app_configurations(
+        context,
+        session_id,
+        tlvs.len() as u32,
+        app_configs.len() as u32,
+        app_configs,
+    )?;
+    status_code_to_res(res.get_status())
+}
+
+/// App configs FiRa allows to change while the session is active.
+const ACTIVE_RECONFIGURABLE_APP_CONFIGS: [AppConfigTlvType; 4] = [
+    AppConfigTlvType::BlockStrideLength,
+    AppConfigTlvType::RangeDataNtfConfig,
+    AppConfigTlvType::RangeDataNtfProximityNear,
+    AppConfigTlvType::RangeDataNtfProximityFar,
+];
+
+/// Apply only the TLVs of the requested config set that differ from the configs last applied to
+/// the session, and return the status of the update together with the TLVs actually sent.
+fn reconfigure_session<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    app_config_params: jbyteArray,
+) -> Result<(StatusCode, Vec<(u8, Vec<u8>)>), UwbErr> {
+    let app_configs = context.convert_byte_array(app_config_params)?;
+    let tlvs = parse_app_config_tlvs(&app_configs)?;
+    let cfg_ids: Vec<u8> = tlvs.iter().map(|(cfg_id, _)| *cfg_id).collect();
+    let cached = context.get_caches()?.app_configs.get(session_id, &cfg_ids).unwrap_or_default();
+    let delta: Vec<(u8, Vec<u8>)> = tlvs
+        .into_iter()
+        .filter(|(cfg_id, value)| {
+            !cached.iter().any(|(id, v)| id == cfg_id && v.as_slice() == *value)
+        })
+        .map(|(cfg_id, value)| (cfg_id, value.to_vec()))
+        .collect();
+    if delta.is_empty() {
+        return Ok((StatusCode::UciStatusOk, Vec::new()));
+    }
+
+    if get_session_state(context, session_id)? == SessionState::SessionStateActive as jbyte {
+        if let Some((cfg_id, _)) = delta.iter().find(|(cfg_id, _)| {
+            !ACTIVE_RECONFIGURABLE_APP_CONFIGS.iter().any(|id| *id as u8 == *cfg_id)
+        }) {
+            error!("App config {:#04x} cannot be changed while session is active", cfg_id);
+            return Err(UwbErr::StatusCode(StatusCode::UciStatusRejected));
+        }
+    }
+
+    let delta_configs = encode_app_config_tlvs(&delta);
+    let res = apply_app_configurations(
+        context,
+        session_id,
+        delta.len() as u32,
+        delta_configs.len() as u32,
+        delta_configs,
+    )?;
+    Ok((res.get_status(), delta))
+}
+
+/// App configs carrying key material, left out of exported session configs: VENDOR_ID and
+/// STATIC_STS_IV, from which the static STS is derived, and the FiRa 2.0 SESSION_KEY (0x45) and
+/// SUB_SESSION_KEY (0x46).
+const KEY_APP_CONFIGS: [u8; 4] =
+    [AppConfigTlvType::VendorId as u8, AppConfigTlvType::StaticStsIv as u8, 0x45, 0x46];
+
+/// Snapshot the configs last applied to the session, without the key material.
+fn export_session_config<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+) -> Result<Vec<u8>, UwbErr> {
+    let app_configs = &context.get_caches()?.app_configs;
+    let (session_type, configs) =
+        match (app_configs.session_type(session_id), app_configs.get(session_id, &[])) {
+            (Some(session_type), Some(configs)) => (session_type, configs),
+            _ => {
+                error!("Session {} was not initialized through the native layer", session_id);
+                return Err(UwbErr::StatusCode(StatusCode::UciStatusSessionNotExist));
+            }
+        };
+    let configs: Vec<(u8, Vec<u8>)> =
+        configs.into_iter().filter(|(cfg_id, _)| !KEY_APP_CONFIGS.contains(cfg_id)).collect();
+    let uci_version = get_device_info(context, false)?.get_uci_version();
+    Ok(SessionConfigBlob {
+        session_type,
+        uci_version,
+        app_configs: encode_app_config_tlvs(&configs),
+    }
+    .encode())
+}
+
+/// Open a session of the exported type and apply the exported configs to it. The session is
+/// deinitialized again if the configs are not accepted.
+fn import_session_config<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    blob: jbyteArray,
+) -> Result<(), UwbErr> {
+    let blob = SessionConfigBlob::decode(&context.convert_byte_array(blob)?)?;
+    open_session_from_config(context, session_id, blob)
+}
+
+fn open_session_from_config<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    blob: SessionConfigBlob,
+) -> Result<(), UwbErr> {
+    let tlvs = parse_app_config_tlvs(&blob.app_configs)?;
+    let no_of_params = tlvs.len() as u32;
+    let local_uci_version = get_device_info(context, false)?.get_uci_version();
+    if !blob.is_compatible_with(local_uci_version) {
+        error!(
+            "Session config exported for UCI version {:#06x}, local UWBS has {:#06x}",
+            blob.uci_version, local_uci_version
+        );
+        return Err(UwbErr::StatusCode(StatusCode::UciStatusRejected));
+    }
+    let caps_info = get_caps_info(context)?;
+    status_code_to_res(caps_info.get_status())?;
+    check_configs_against_caps(&tlvs, caps_info.get_tlvs())?;
+
+    session_init(context, session_id, blob.session_type)?;
+    let app_config_param_len = blob.app_configs.len() as u32;
+    let res = apply_app_configurations(
+        context,
         session_id,
         no_of_params,
         app_config_param_len,
-        app_configs,
-    })? {
-        UciResponse::SessionGetAppConfigRsp(data) => Ok(data),
-        _ => Err(UwbErr::failed()),
+        blob.app_configs,
+    )
+    .and_then(|res| status_code_to_res(res.get_status()));
+    if res.is_err() {
+        if let Err(e) = session_deinit(context, session_id) {
+            error!("Failed to deinit session {} after import failure: {:?}", session_id, e);
+        }
+    }
+    res
+}
+
+/// Check that the UWBS advertises support for the STS mode and channel the configs ask for. A
+/// capability the UWBS does not report is not checked.
+fn check_configs_against_caps(tlvs: &[(u8, &[u8])], caps: &[CapTlv]) -> Result<(), UwbErr> {
+    let supported = |cap_type: CapTlvType, bit: Option<u8>| match caps
+        .iter()
+        .find(|tlv| tlv.t == cap_type)
+        .and_then(|tlv| tlv.v.first())
+    {
+        Some(mask) => bit.map_or(false, |bit| mask & (1 << bit) != 0),
+        None => true,
+    };
+    for (cfg_id, value) in tlvs {
+        let (name, is_supported) = match (*cfg_id, *value) {
+            // SUPPORTED_STS_CONFIG has one bit per STS_CONFIG value.
+            (cfg_id, [sts_config]) if cfg_id == AppConfigTlvType::StsConfig as u8 => (
+                "STS_CONFIG",
+                supported(CapTlvType::SupportedStsConfig, Some(*sts_config).filter(|v| *v < 8)),
+            ),
+            (cfg_id, [channel]) if cfg_id == AppConfigTlvType::ChannelNumber as u8 => (
+                "CHANNEL_NUMBER",
+                supported(CapTlvType::SupportedChannels, supported_channels_bit(*channel)),
+            ),
+            _ => continue,
+        };
+        if !is_supported {
+            error!("{} {:02x?} is not supported by the UWBS", name, value);
+            return Err(UwbErr::StatusCode(StatusCode::UciStatusRejected));
+        }
+    }
+    Ok(())
+}
+
+/// Return the bit of the channel in the SUPPORTED_CHANNELS capability.
+fn supported_channels_bit(channel: u8) -> Option<u8> {
+    [5, 6, 8, 9, 10, 12, 13, 14].iter().position(|c| *c == channel).map(|bit| bit as u8)
+}
+
+fn apply_block_stride<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    block_stride: u8,
+) -> Result<(), UwbErr> {
+    let app_configs =
+        encode_app_config_tlvs(&[(AppConfigTlvType::BlockStrideLength as u8, vec![block_stride])]);
+    let res =
+        apply_app_configurations(context, session_id, 1, app_configs.len() as u32, app_configs)?;
+    status_code_to_res(res.get_status())
+}
+
+/// Opt the session in the idle governor with the given block stride, or out of it if the block
+/// stride is negative. A session opting out while throttled gets its block stride restored.
+fn set_idle_block_stride<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    idle_block_stride: i32,
+) -> Result<(), UwbErr> {
+    if idle_block_stride < 0 {
+        return match IDLE_GOVERNOR.opt_out(session_id) {
+            Some(block_stride) => apply_block_stride(context, session_id, block_stride),
+            None => Ok(()),
+        };
     }
+    let idle_block_stride = u8::try_from(idle_block_stride).map_err(|_| {
+        error!("Invalid idle block stride {}", idle_block_stride);
+        UwbErr::StatusCode(StatusCode::UciStatusInvalidParam)
+    })?;
+    IDLE_GOVERNOR.opt_in(session_id, idle_block_stride);
+    Ok(())
+}
+
+/// Apply the block stride changes the idle governor decides on for the new interactive state. A
+/// failure on one session is logged and does not prevent the others from being updated.
+fn set_device_interactive<'a, T: Context<'a>>(context: &T, interactive: bool) {
+    let caches = match context.get_caches() {
+        Ok(caches) => caches,
+        Err(e) => {
+            error!("Cannot apply interactive state {}: {:?}", interactive, e);
+            return;
+        }
+    };
+    let changes = IDLE_GOVERNOR.set_interactive(interactive, |session_id| {
+        caches
+            .app_configs
+            .get(session_id, &[AppConfigTlvType::BlockStrideLength as u8])
+            .and_then(|configs| configs.first().and_then(|(_, v)| v.first().copied()))
+            .unwrap_or(0)
+    });
+    for (session_id, block_stride) in changes {
+        if let Err(e) = apply_block_stride(context, session_id, block_stride) {
+            error!(
+                "Failed to set block stride {} on session {}: {:?}",
+                block_stride, session_id, e
+            );
+        }
+    }
+}
+
+/// Validate and store a provisioned profile, so that sessions can later be opened from it without
+/// Java providing the parameters again.
+fn store_profile<'a, T: Context<'a>>(
+    context: &T,
+    profile_id: u32,
+    blob: jbyteArray,
+) -> Result<(), UwbErr> {
+    let profile = SessionConfigBlob::decode(&context.convert_byte_array(blob)?)?;
+    validate_session_type(profile.session_type)?;
+    validate_sts_configs(&parse_app_config_tlvs(&profile.app_configs)?)?;
+    PROFILES.store(profile_id, profile);
+    Ok(())
+}
+
+fn open_session_with_profile<'a, T: Context<'a>>(
+    context: &T,
+    session_id: u32,
+    profile_id: u32,
+) -> Result<(), UwbErr> {
+    let profile = PROFILES.get(profile_id).ok_or_else(|| {
+        error!("No profile {} provisioned", profile_id);
+        UwbErr::StatusCode(StatusCode::UciStatusInvalidParam)
+    })?;
+    open_session_from_config(context, session_id, profile)
 }
 
+/// Return the capabilities of the UWBS, from the cache if they were fetched since the UWBS was
+/// last initialized or reset.
 fn get_caps_info<'a, T: Context<'a>>(context: &T) -> Result<GetCapsInfoRspPacket, UwbErr> {
+    let caches = context.get_caches()?;
+    if let Some(caps_info) = caches.caps_info.lock().unwrap().as_ref() {
+        return Ok(caps_info.clone());
+    }
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciGetCapsInfo)? {
-        UciResponse::GetCapsInfoRsp(data) => Ok(data),
+    match block_on_command(dispatcher, JNICommand::UciGetCapsInfo)? {
+        UciResponse::GetCapsInfoRsp(data) => {
+            if data.get_status() == StatusCode::UciStatusOk {
+                *caches.caps_info.lock().unwrap() = Some(data.clone());
+            }
+            Ok(data)
+        }
         _ => Err(UwbErr::failed()),
     }
 }
@@ -672,20 +1861,113 @@ fn multicast_list_update<'a, T: Context<'a>>(
     let mut sub_session_id_list =
         vec![0i32; context.get_array_length(sub_session_ids)?.try_into().unwrap()];
     context.get_int_array_region(sub_session_ids, 0, &mut sub_session_id_list)?;
+    let (address_list, sub_session_id_list) =
+        validate_controlees(no_of_controlee, &address_list, &sub_session_id_list)?;
+    let sts_config = context
+        .get_caches()?
+        .app_configs
+        .get(session_id, &[AppConfigTlvType::StsConfig as u8])
+        .and_then(|configs| configs.first().and_then(|(_, value)| value.first().copied()));
+    validate_controlee_sub_session_ids(sts_config, &sub_session_id_list)?;
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciSessionUpdateMulticastList {
-        session_id,
-        action,
-        no_of_controlee,
-        address_list: address_list.to_vec(),
-        sub_session_id_list: sub_session_id_list.to_vec(),
-    })? {
+    let res = match block_on_command(
+        dispatcher,
+        JNICommand::UciSessionUpdateMulticastList {
+            session_id,
+            action,
+            no_of_controlee: address_list.len() as u8,
+            address_list,
+            sub_session_id_list,
+        },
+    )? {
         UciResponse::SessionUpdateControllerMulticastListRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
     status_code_to_res(res.get_status())
 }
 
+/// Cross-check the controlee arrays received from Java before they reach the UWBS.
+///
+/// Entries repeating an earlier address with the same sub-session id are dropped. Any other
+/// inconsistency is logged together with the offending entry and rejected as an invalid param.
+fn validate_controlees(
+    no_of_controlee: u8,
+    address_list: &[i16],
+    sub_session_id_list: &[i32],
+) -> Result<(Vec<i16>, Vec<i32>), UwbErr> {
+    if address_list.len() != no_of_controlee as usize {
+        error!(
+            "Controlee count {} does not match address list length {}",
+            no_of_controlee,
+            address_list.len()
+        );
+        return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+    }
+    if sub_session_id_list.len() != address_list.len() {
+        error!(
+            "Sub-session id list length {} does not match address list length {}",
+            sub_session_id_list.len(),
+            address_list.len()
+        );
+        return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+    }
+    let mut addresses: Vec<i16> = Vec::new();
+    let mut sub_session_ids: Vec<i32> = Vec::new();
+    for (idx, (&address, &sub_session_id)) in
+        address_list.iter().zip(sub_session_id_list.iter()).enumerate()
+    {
+        if address as u16 == 0x0000 || address as u16 == 0xFFFF {
+            error!("Controlee {} has reserved address {:#06x}", idx, address as u16);
+            return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+        }
+        match addresses.iter().position(|&a| a == address) {
+            Some(prev) if sub_session_ids[prev] == sub_session_id => {
+                info!("Dropping controlee {} duplicating controlee {}", idx, prev);
+            }
+            Some(prev) => {
+                error!(
+                    "Controlee {} reuses address {:#06x} of controlee {} with a new sub-session id",
+                    idx, address as u16, prev
+                );
+                return Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam));
+            }
+            None => {
+                addresses.push(address);
+                sub_session_ids.push(sub_session_id);
+            }
+        }
+    }
+    Ok((addresses, sub_session_ids))
+}
+
+/// Check the controlee sub-session ids against the STS_CONFIG cached for the session. Dynamic STS
+/// with responder specific sub-session keys needs an id for every controlee, other STS configs
+/// take none, which Java sends as zeros. Nothing is checked without a cached STS_CONFIG.
+fn validate_controlee_sub_session_ids(
+    sts_config: Option<u8>,
+    sub_session_id_list: &[i32],
+) -> Result<(), UwbErr> {
+    let sts_config = match sts_config {
+        Some(sts_config) => sts_config,
+        None => return Ok(()),
+    };
+    let expects_ids = sts_config == 0x02;
+    match sub_session_id_list.iter().position(|&id| (id != 0) != expects_ids) {
+        Some(idx) if expects_ids => {
+            error!("Controlee {} has no sub-session id, required by STS_CONFIG 0x02", idx);
+            Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+        }
+        Some(idx) => {
+            error!(
+                "Controlee {} has a sub-session id, not used with STS_CONFIG {:#04x}",
+                idx, sts_config
+            );
+            Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+        }
+        None => Ok(()),
+    }
+}
+
 fn set_country_code<'a, T: Context<'a>>(
     context: &T,
     country_code: jbyteArray,
@@ -695,7 +1977,7 @@ fn set_country_code<'a, T: Context<'a>>(
         return Err(UwbErr::failed());
     }
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciSetCountryCode { code })? {
+    let res = match block_on_command(dispatcher, JNICommand::UciSetCountryCode { code })? {
         UciResponse::AndroidSetCountryCodeRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
@@ -739,7 +2021,7 @@ fn send_raw_vendor_cmd<'a, T: Context<'a>>(
 ) -> Result<(i32, i32, Vec<u8>), UwbErr> {
     let payload = context.convert_byte_array(payload)?;
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciRawVendorCmd { gid, oid, payload })? {
+    match block_on_command(dispatcher, JNICommand::UciRawVendorCmd { gid, oid, payload })? {
         UciResponse::RawVendorRsp(response) => Ok((
             response.get_group_id().to_i32().unwrap(),
             response.get_opcode().to_i32().unwrap(),
@@ -749,6 +2031,69 @@ fn send_raw_vendor_cmd<'a, T: Context<'a>>(
     }
 }
 
+/// Send the command and record its latency and outcome in UCI_METRICS.
+fn block_on_command(dispatcher: &dyn Dispatcher, cmd: JNICommand) -> Result<UciResponse, UwbErr> {
+    record_command(dispatcher, cmd, &UCI_METRICS, &MonotonicClock)
+}
+
+fn record_command(
+    dispatcher: &dyn Dispatcher,
+    cmd: JNICommand,
+    metrics: &UciMetrics,
+    clock: &dyn Clock,
+) -> Result<UciResponse, UwbErr> {
+    let command = command_name(&cmd);
+    let start = clock.now();
+    let res = dispatcher.block_on_jni_command(cmd);
+    metrics.record(command, clock.now() - start, res.as_ref().map(response_status));
+    res
+}
+
+/// Return the status the UWBS answered with, if the response carries one.
+fn response_status(response: &UciResponse) -> Option<StatusCode> {
+    match response {
+        UciResponse::GetDeviceInfoRsp(data) => Some(data.get_status()),
+        UciResponse::GetCapsInfoRsp(data) => Some(data.get_status()),
+        UciResponse::DeviceResetRsp(data) => Some(data.get_status()),
+        UciResponse::SessionInitRsp(data) => Some(data.get_status()),
+        UciResponse::SessionDeinitRsp(data) => Some(data.get_status()),
+        UciResponse::SessionGetAppConfigRsp(data) => Some(data.get_status()),
+        UciResponse::SessionSetAppConfigRsp(data) => Some(data.get_status()),
+        UciResponse::SessionGetStateRsp(data) => Some(data.get_status()),
+        UciResponse::SessionGetCountRsp(data) => Some(data.get_status()),
+        UciResponse::SessionUpdateControllerMulticastListRsp(data) => Some(data.get_status()),
+        UciResponse::RangeStartRsp(data) => Some(data.get_status()),
+        UciResponse::RangeStopRsp(data) => Some(data.get_status()),
+        UciResponse::AndroidSetCountryCodeRsp(data) => Some(data.get_status()),
+        UciResponse::AndroidGetPowerStatsRsp(data) => Some(data.get_stats().status),
+        // The status of a vendor response is part of its opaque payload.
+        UciResponse::RawVendorRsp(_) => None,
+        // Responses to commands this layer does not send.
+        _ => None,
+    }
+}
+
+fn command_name(cmd: &JNICommand) -> &'static str {
+    match cmd {
+        JNICommand::UciDeviceReset { .. } => "UciDeviceReset",
+        JNICommand::UciGetAppConfig { .. } => "UciGetAppConfig",
+        JNICommand::UciGetCapsInfo => "UciGetCapsInfo",
+        JNICommand::UciGetDeviceInfo => "UciGetDeviceInfo",
+        JNICommand::UciGetPowerStats => "UciGetPowerStats",
+        JNICommand::UciGetSessionState(..) => "UciGetSessionState",
+        JNICommand::UciRawVendorCmd { .. } => "UciRawVendorCmd",
+        JNICommand::UciSessionDeinit(..) => "UciSessionDeinit",
+        JNICommand::UciSessionGetCount => "UciSessionGetCount",
+        JNICommand::UciSessionInit(..) => "UciSessionInit",
+        JNICommand::UciSessionUpdateMulticastList { .. } => "UciSessionUpdateMulticastList",
+        JNICommand::UciSetAppConfig { .. } => "UciSetAppConfig",
+        JNICommand::UciSetCountryCode { .. } => "UciSetCountryCode",
+        JNICommand::UciStartRange(..) => "UciStartRange",
+        JNICommand::UciStopRange(..) => "UciStopRange",
+        _ => "Other",
+    }
+}
+
 fn status_code_to_res(status_code: StatusCode) -> Result<(), UwbErr> {
     match status_code {
         StatusCode::UciStatusOk => Ok(()),
@@ -770,7 +2115,7 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeDi
         }
     };
     match DispatcherImpl::new(eventmanager) {
-        Ok(dispatcher) => Box::into_raw(Box::new(dispatcher)) as jlong,
+        Ok(dispatcher) => DISPATCHERS.insert(Box::new(NativeStack::new(dispatcher))),
         Err(err) => {
             error!("Fail to create dispatcher {:?}", err);
             *JObject::null() as jlong
@@ -784,31 +2129,81 @@ pub extern "system" fn Java_com_android_server_uwb_jni_NativeUwbManager_nativeDi
     env: JNIEnv,
     obj: JObject,
 ) {
-    let dispatcher_ptr_value = match env.get_field(obj, "mDispatcherPointer", "J") {
+    let dispatcher_handle_value = match env.get_field(obj, "mDispatcherPointer", "J") {
         Ok(value) => value,
         Err(err) => {
-            error!("Failed to get the pointer with: {:?}", err);
+            error!("Failed to get the handle with: {:?}", err);
             return;
         }
     };
-    let dispatcher_ptr = match dispatcher_ptr_value.j() {
+    let dispatcher_handle = match dispatcher_handle_value.j() {
         Ok(value) => value,
         Err(err) => {
-            error!("Failed to get the pointer with: {:?}", err);
+            error!("Failed to get the handle with: {:?}", err);
             return;
         }
     };
-    // Safety: dispatcher pointer must not be a null pointer and must point to a valid dispatcher object.
-    // This can be ensured because the dispatcher is created in an earlier stage and
-    // won't be deleted before calling this destroy function.
-    // This function will early return if the instance is already destroyed.
-    let _boxed_dispatcher = unsafe { Box::from_raw(dispatcher_ptr as *mut DispatcherImpl) };
-    info!("The dispatcher successfully destroyed.");
+    if release_native_stack(dispatcher_handle).is_err() {
+        throw_stale_dispatcher(&env, dispatcher_handle);
+    }
+}
+
+/// Release the dispatcher and caches behind the handle. A zero handle means there is nothing to
+/// release, e.g. the dispatcher was never created.
+fn release_native_stack(dispatcher_handle: jlong) -> Result<(), UwbErr> {
+    if dispatcher_handle == 0 {
+        info!("No dispatcher to destroy.");
+        return Ok(());
+    }
+    // The handle table rejects the handle if the instance is already destroyed.
+    match DISPATCHERS.remove(dispatcher_handle) {
+        Some(_boxed_native_stack) => {
+            info!("The dispatcher successfully destroyed.");
+            Ok(())
+        }
+        None => Err(UwbErr::NoneDispatcher),
+    }
+}
+
+/// Check the dispatcher handle before an entry point uses it. For a stale handle this raises an
+/// IllegalStateException in Java and returns false, the entry point must then return right away
+/// without any other JNI call. A zero handle is left to the helpers, which report the stack as not
+/// initialized.
+fn ensure_live_dispatcher(env: &JNIEnv, obj: JObject) -> bool {
+    let dispatcher_handle =
+        match env.get_field(obj, "mDispatcherPointer", "J").and_then(|value| value.j()) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to get the handle with: {:?}", err);
+                return false;
+            }
+        };
+    if is_stale_handle(dispatcher_handle) {
+        throw_stale_dispatcher(env, dispatcher_handle);
+        return false;
+    }
+    true
+}
+
+fn is_stale_handle(dispatcher_handle: jlong) -> bool {
+    dispatcher_handle != 0 && DISPATCHERS.get(dispatcher_handle).is_none()
+}
+
+/// Raise an IllegalStateException in Java for a dispatcher handle that is no longer live. Java
+/// resets mDispatcherPointer when destroying the dispatcher, so this is always a caller bug.
+fn throw_stale_dispatcher(env: &JNIEnv, dispatcher_handle: jlong) {
+    error!("The dispatcher handle {} is not live.", dispatcher_handle);
+    if let Err(err) = env.throw_new(
+        "java/lang/IllegalStateException",
+        format!("UWB dispatcher handle {} is stale, it was already destroyed", dispatcher_handle),
+    ) {
+        error!("Failed to throw IllegalStateException with: {:?}", err);
+    }
 }
 
 fn get_power_stats<'a, T: Context<'a>>(context: &T) -> Result<[JValue<'a>; 4], UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    match dispatcher.block_on_jni_command(JNICommand::UciGetPowerStats)? {
+    match block_on_command(dispatcher, JNICommand::UciGetPowerStats)? {
         UciResponse::AndroidGetPowerStatsRsp(data) => Ok([
             JValue::Int(data.get_stats().idle_time_ms as i32),
             JValue::Int(data.get_stats().tx_time_ms as i32),
@@ -820,28 +2215,34 @@ fn get_power_stats<'a, T: Context<'a>>(context: &T) -> Result<[JValue<'a>; 4], U
 }
 
 fn uwa_get_device_info(dispatcher: &dyn Dispatcher) -> Result<UciResponse, UwbErr> {
-    let res = dispatcher.block_on_jni_command(JNICommand::UciGetDeviceInfo)?;
+    let res = block_on_command(dispatcher, JNICommand::UciGetDeviceInfo)?;
     Ok(res)
 }
 
 fn reset_device<'a, T: Context<'a>>(context: &T, reset_config: u8) -> Result<(), UwbErr> {
     let dispatcher = context.get_dispatcher()?;
-    let res = match dispatcher.block_on_jni_command(JNICommand::UciDeviceReset { reset_config })? {
+    let res = match block_on_command(dispatcher, JNICommand::UciDeviceReset { reset_config })? {
         UciResponse::DeviceResetRsp(data) => data,
         _ => return Err(UwbErr::failed()),
     };
-    status_code_to_res(res.get_status())
+    status_code_to_res(res.get_status())?;
+    // The reset dropped every session on the UWBS.
+    context.get_caches()?.clear();
+    Ok(())
 }
 
 #[cfg(test)]
 mod mock_context;
 #[cfg(test)]
 mod mock_dispatcher;
+#[cfg(test)]
+mod uci_golden_tests;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::clock::FakeClock;
     use crate::mock_context::MockContext;
     use crate::mock_dispatcher::MockDispatcher;
 
@@ -865,7 +2266,43 @@ mod tests {
     }
 
     #[test]
-    fn test_do_initialize() {
+    fn test_is_allowed_by_policy() {
+        let policy = PolicyOperation::DeviceControl as u32 | PolicyOperation::SessionControl as u32;
+        assert!(is_allowed_by_policy(policy, PolicyOperation::DeviceControl));
+        assert!(is_allowed_by_policy(policy, PolicyOperation::SessionControl));
+        assert!(!is_allowed_by_policy(policy, PolicyOperation::RawVendorCommand));
+        assert!(is_allowed_by_policy(u32::MAX, PolicyOperation::RawVendorCommand));
+        assert!(!is_allowed_by_policy(0, PolicyOperation::SessionControl));
+    }
+
+    #[test]
+    fn test_native_stack_info() {
+        let info = native_stack_info(None);
+        assert!(
+            info.starts_with(&format!("crate: uwb_uci_jni_rust {}\n", env!("CARGO_PKG_VERSION")))
+        );
+        assert!(info.contains(&format!("data_transfer: {}\n", cfg!(feature = "data_transfer"))));
+        assert!(info.contains(&format!("radar: {}\n", cfg!(feature = "radar"))));
+        assert!(info.contains(&format!("mock_chip: {}\n", cfg!(feature = "mock_chip"))));
+        assert!(info.contains("live_dispatcher_handles: "));
+    }
+
+    #[test]
+    fn test_is_stale_handle() {
+        // A zero handle is reported by the helpers as not initialized, not thrown.
+        assert!(!is_stale_handle(0));
+        assert!(is_stale_handle(jlong::MAX));
+    }
+
+    #[test]
+    fn test_release_native_stack() {
+        // Destroying before the dispatcher was created, or twice through Java, is a no-op.
+        assert!(release_native_stack(0).is_ok());
+        assert!(release_native_stack(jlong::MAX).is_err());
+    }
+
+    #[test]
+    fn test_do_initialize() {
         let packet = uwb_uci_packets::GetDeviceInfoRspBuilder {
             status: StatusCode::UciStatusOk,
             uci_version: 0,
@@ -888,6 +2325,8 @@ mod tests {
         let device_info = context.get_mock_dispatcher().get_device_info().clone();
         assert!(result.is_ok());
         assert_eq!(device_info.unwrap().to_vec(), packet.to_vec());
+        // Caps are fetched on first use, not on initialize.
+        assert!(context.get_caches().unwrap().caps_info.lock().unwrap().is_none());
     }
 
     #[test]
@@ -896,9 +2335,11 @@ mod tests {
         dispatcher.expect_send_jni_command(JNICommand::Disable(true), Ok(()));
         dispatcher.expect_wait_for_exit(Ok(()));
         let context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.init_session(1234, 0x00);
 
         let result = do_deinitialize(&context);
         assert!(result.is_ok());
+        assert_eq!(context.get_caches().unwrap().app_configs.session_count(), 0);
     }
 
     #[test]
@@ -933,6 +2374,283 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_device_info() {
+        let cached = uwb_uci_packets::GetDeviceInfoRspBuilder {
+            status: StatusCode::UciStatusOk,
+            uci_version: 0x1001,
+            mac_version: 0,
+            phy_version: 0,
+            uci_test_version: 0,
+            vendor_spec_info: vec![1, 2],
+        }
+        .build();
+        let refreshed = uwb_uci_packets::GetDeviceInfoRspBuilder {
+            status: StatusCode::UciStatusOk,
+            uci_version: 0x1002,
+            mac_version: 0,
+            phy_version: 0,
+            uci_test_version: 0,
+            vendor_spec_info: vec![3, 4, 5],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(cached.clone()));
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetDeviceInfo,
+            Ok(UciResponse::GetDeviceInfoRsp(refreshed.clone())),
+        );
+        let context = MockContext::new(dispatcher);
+
+        let result = get_device_info(&context, false).unwrap();
+        assert_eq!(result.to_vec(), cached.to_vec());
+        let result = get_device_info(&context, true).unwrap();
+        assert_eq!(result.to_vec(), refreshed.to_vec());
+        assert_eq!(result.get_vendor_spec_info(), &[3, 4, 5]);
+    }
+
+    #[test]
+    fn test_get_device_info_refresh_failed() {
+        let cached = device_info_with_uci_version(0x1001);
+        let failed = uwb_uci_packets::GetDeviceInfoRspBuilder {
+            status: StatusCode::UciStatusFailed,
+            uci_version: 0,
+            mac_version: 0,
+            phy_version: 0,
+            uci_test_version: 0,
+            vendor_spec_info: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(cached.clone()));
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetDeviceInfo,
+            Ok(UciResponse::GetDeviceInfoRsp(failed)),
+        );
+        let context = MockContext::new(dispatcher);
+
+        let result = get_device_info(&context, true);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusFailed))));
+        let result = get_device_info(&context, false).unwrap();
+        assert_eq!(result.to_vec(), cached.to_vec());
+    }
+
+    fn device_info_with_uci_version(uci_version: u16) -> GetDeviceInfoRspPacket {
+        uwb_uci_packets::GetDeviceInfoRspBuilder {
+            status: StatusCode::UciStatusOk,
+            uci_version,
+            mac_version: 0,
+            phy_version: 0,
+            uci_test_version: 0,
+            vendor_spec_info: vec![],
+        }
+        .build()
+    }
+
+    fn caps_info_with_tlvs(tlvs: Vec<CapTlv>) -> GetCapsInfoRspPacket {
+        uwb_uci_packets::GetCapsInfoRspBuilder { status: StatusCode::UciStatusOk, tlvs }.build()
+    }
+
+    #[test]
+    fn test_export_import_session_config() {
+        let (exported_id, imported_id) = (1234, 5678);
+        let app_configs = vec![0x02, 1, 0x01, 0x11, 1, 0x00];
+        let fake_blob = std::ptr::null_mut();
+        let init_packet =
+            uwb_uci_packets::SessionInitRspBuilder { status: StatusCode::UciStatusOk }.build();
+        let set_packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(device_info_with_uci_version(0x1001)));
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSessionInit(imported_id, 0x00),
+            Ok(UciResponse::SessionInitRsp(init_packet)),
+        );
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id: imported_id,
+                no_of_params: 2,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs: app_configs.clone(),
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(set_packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        *context.get_caches().unwrap().caps_info.lock().unwrap() =
+            Some(caps_info_with_tlvs(vec![CapTlv {
+                t: CapTlvType::SupportedStsConfig,
+                v: vec![0x03],
+            }]));
+        let cache = &context.get_caches().unwrap().app_configs;
+        cache.init_session(exported_id, 0x00);
+        cache.update(exported_id, &parse_app_config_tlvs(&app_configs).unwrap());
+        // Key material is never exported.
+        cache.update(exported_id, &[(0x27, &[0x08, 0x07][..]), (0x45, &[0x01; 16][..])]);
+
+        let blob = export_session_config(&context, exported_id).unwrap();
+        assert_eq!(blob, [&[0x01, 0x00, 0x01, 0x10][..], &app_configs].concat());
+        context.expect_convert_byte_array(fake_blob, Ok(blob));
+        assert!(import_session_config(&context, imported_id, fake_blob).is_ok());
+        let cache = &context.get_caches().unwrap().app_configs;
+        assert_eq!(cache.session_type(imported_id), Some(0x00));
+        assert_eq!(cache.get(imported_id, &[0x11]).unwrap(), vec![(0x11, vec![0x00])]);
+    }
+
+    #[test]
+    fn test_import_session_config_incompatible() {
+        let fake_blob = std::ptr::null_mut();
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(device_info_with_uci_version(0x1002)));
+        let mut context = MockContext::new(dispatcher);
+        context.expect_convert_byte_array(fake_blob, Ok(vec![0x01, 0x00, 0x01, 0x10]));
+
+        let result = import_session_config(&context, 1234, fake_blob);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusRejected))));
+    }
+
+    #[test]
+    fn test_import_session_config_unsupported_by_caps() {
+        let fake_blob = std::ptr::null_mut();
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(device_info_with_uci_version(0x1001)));
+        // Only channel 9 is supported.
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetCapsInfo,
+            Ok(UciResponse::GetCapsInfoRsp(caps_info_with_tlvs(vec![CapTlv {
+                t: CapTlvType::SupportedChannels,
+                v: vec![0x08],
+            }]))),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context.expect_convert_byte_array(fake_blob, Ok(vec![0x01, 0x00, 0x01, 0x10, 0x04, 1, 5]));
+
+        let result = import_session_config(&context, 1234, fake_blob);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusRejected))));
+    }
+
+    #[test]
+    fn test_check_configs_against_caps() {
+        let caps = vec![
+            CapTlv { t: CapTlvType::SupportedStsConfig, v: vec![0x02] },
+            CapTlv { t: CapTlvType::SupportedChannels, v: vec![0x0C] },
+        ];
+        let check = |tlvs: &[u8], caps: &[CapTlv]| {
+            check_configs_against_caps(&parse_app_config_tlvs(tlvs).unwrap(), caps)
+        };
+
+        assert!(check(&[0x02, 1, 0x01, 0x04, 1, 8, 0x04, 1, 9], &caps).is_ok());
+        assert!(check(&[0x02, 1, 0x00], &caps).is_err());
+        assert!(check(&[0x02, 1, 0x02], &caps).is_err());
+        assert!(check(&[0x04, 1, 5], &caps).is_err());
+        assert!(check(&[0x04, 1, 7], &caps).is_err());
+        // Configs without a matching capability are not checked.
+        assert!(check(&[0x02, 1, 0x00, 0x04, 1, 5, 0x11, 1, 0x00], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_export_session_config_unknown_session() {
+        let context = MockContext::new(MockDispatcher::new());
+        assert!(export_session_config(&context, 1234).is_err());
+    }
+
+    #[test]
+    fn test_open_session_with_profile() {
+        let (profile_id, session_id) = (7, 1234);
+        let app_configs = vec![0x02, 1, 0x01];
+        let blob = SessionConfigBlob {
+            session_type: 0x00,
+            uci_version: 0x1001,
+            app_configs: app_configs.clone(),
+        };
+        let fake_blob = std::ptr::null_mut();
+        let init_packet =
+            uwb_uci_packets::SessionInitRspBuilder { status: StatusCode::UciStatusOk }.build();
+        let set_packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.set_device_info(Some(device_info_with_uci_version(0x1001)));
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSessionInit(session_id, 0x00),
+            Ok(UciResponse::SessionInitRsp(init_packet)),
+        );
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 1,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs,
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(set_packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        *context.get_caches().unwrap().caps_info.lock().unwrap() =
+            Some(caps_info_with_tlvs(vec![]));
+        context.expect_convert_byte_array(fake_blob, Ok(blob.encode()));
+
+        store_profile(&context, profile_id, fake_blob).unwrap();
+        assert!(PROFILES.ids().contains(&profile_id));
+        assert!(open_session_with_profile(&context, session_id, profile_id).is_ok());
+        assert!(PROFILES.remove(profile_id));
+        assert!(open_session_with_profile(&context, session_id, profile_id).is_err());
+    }
+
+    #[test]
+    fn test_store_profile_invalid() {
+        let fake_blob = std::ptr::null_mut();
+        let mut context = MockContext::new(MockDispatcher::new());
+        // Static STS without VENDOR_ID and STATIC_STS_IV.
+        let blob = SessionConfigBlob {
+            session_type: 0x00,
+            uci_version: 0x1001,
+            app_configs: vec![0x02, 1, 0x00],
+        };
+        context.expect_convert_byte_array(fake_blob, Ok(blob.encode()));
+
+        assert!(store_profile(&context, 8, fake_blob).is_err());
+        assert!(!PROFILES.ids().contains(&8));
+    }
+
+    #[test]
+    fn test_set_device_interactive() {
+        let session_id = 1234;
+        let set_packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        for block_stride in [0x05, 0x01] {
+            dispatcher.expect_block_on_jni_command(
+                JNICommand::UciSetAppConfig {
+                    session_id,
+                    no_of_params: 1,
+                    app_config_param_len: 3,
+                    app_configs: vec![0x2D, 1, block_stride],
+                },
+                Ok(UciResponse::SessionSetAppConfigRsp(set_packet.clone())),
+            );
+        }
+        let context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.update(session_id, &[(0x2D, &[0x01][..])]);
+
+        set_idle_block_stride(&context, session_id, 5).unwrap();
+        set_device_interactive(&context, false);
+        set_device_interactive(&context, true);
+        assert!(set_idle_block_stride(&context, session_id, 0x100).is_err());
+        set_idle_block_stride(&context, session_id, -1).unwrap();
+    }
+
     #[test]
     fn test_session_init() {
         let session_id = 1234;
@@ -951,6 +2669,63 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_session_type() {
+        for session_type in [0x00, 0x01, 0x02, 0x05, 0x9F, 0xA0, 0xCF, 0xD0, 0xE0, 0xFF] {
+            assert!(validate_session_type(session_type).is_ok());
+        }
+        for session_type in [0x06, 0x9E, 0xD1, 0xDF] {
+            assert!(validate_session_type(session_type).is_err());
+        }
+    }
+
+    #[test]
+    fn test_session_init_invalid_session_type() {
+        let context = MockContext::new(MockDispatcher::new());
+
+        let result = session_init(&context, 1234, 0x06);
+        assert_eq!(
+            byte_result_helper(result, "SessionInit"),
+            StatusCode::UciStatusInvalidParam.to_i8().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_session_init_max_sessions_exceeded() {
+        let session_id = 1234;
+        let session_type = 5;
+        let init_packet = uwb_uci_packets::SessionInitRspBuilder {
+            status: StatusCode::UciStatusMaxSessionsExceeded,
+        }
+        .build();
+
+        let count_packet = uwb_uci_packets::SessionGetCountRspBuilder {
+            status: StatusCode::UciStatusOk,
+            session_count: 4,
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSessionInit(session_id, session_type),
+            Ok(UciResponse::SessionInitRsp(init_packet)),
+        );
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSessionGetCount,
+            Ok(UciResponse::SessionGetCountRsp(count_packet)),
+        );
+        let context = MockContext::new(dispatcher);
+
+        let (result, uwbs_active_sessions) =
+            session_init_with_count(&context, session_id, session_type);
+        assert_eq!(
+            byte_result_helper(result, "SessionInit"),
+            StatusCode::UciStatusMaxSessionsExceeded.to_i8().unwrap()
+        );
+        assert_eq!(uwbs_active_sessions, Some(4));
+        assert!(UCI_METRICS.summary().contains("last_uwbs_active_sessions=4 jni_session_limit=5"));
+    }
+
     #[test]
     fn test_session_deinit() {
         let session_id = 1234;
@@ -968,6 +2743,52 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_record_command() {
+        let init_packet = uwb_uci_packets::SessionInitRspBuilder {
+            status: StatusCode::UciStatusMaxSessionsExceeded,
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSessionInit(1234, 0x00),
+            Ok(UciResponse::SessionInitRsp(init_packet)),
+        );
+        expect_session_state(&mut dispatcher, 1234, SessionState::SessionStateIdle);
+        dispatcher.expect_block_on_jni_command(JNICommand::UciGetCapsInfo, Err(UwbErr::failed()));
+        let metrics = UciMetrics::new();
+        let clock = FakeClock::new();
+
+        // A UWBS rejection still returns the response, it is only counted under error_statuses.
+        assert!(record_command(
+            &dispatcher,
+            JNICommand::UciSessionInit(1234, 0x00),
+            &metrics,
+            &clock
+        )
+        .is_ok());
+        assert!(record_command(
+            &dispatcher,
+            JNICommand::UciGetSessionState(1234),
+            &metrics,
+            &clock
+        )
+        .is_ok());
+        assert!(record_command(&dispatcher, JNICommand::UciGetCapsInfo, &metrics, &clock).is_err());
+        assert_eq!(
+            metrics.summary(),
+            concat!(
+                "UciGetCapsInfo: count=1 avg_latency_us=0 max_latency_us=0 error_statuses=[] ",
+                "other_errors=1\n",
+                "UciGetSessionState: count=1 avg_latency_us=0 max_latency_us=0 ",
+                "error_statuses=[] other_errors=0\n",
+                "UciSessionInit: count=1 avg_latency_us=0 max_latency_us=0 ",
+                "error_statuses=[0x14=1] other_errors=0\n",
+            )
+        );
+    }
+
     #[test]
     fn test_get_session_count() {
         let session_count = 7;
@@ -986,6 +2807,7 @@ mod tests {
 
         let result = get_session_count(&context).unwrap();
         assert_eq!(result, session_count as jbyte);
+        assert!(UCI_METRICS.summary().contains("UciSessionGetCount: count="));
     }
 
     #[test]
@@ -1043,12 +2865,112 @@ mod tests {
         assert_eq!(result, session_state as jbyte);
     }
 
+    #[test]
+    fn test_wait_for_session_state() {
+        let session_id = 1234;
+        let mut dispatcher = MockDispatcher::new();
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateInit);
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateInit);
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateIdle);
+        let context = MockContext::new(dispatcher);
+        let clock = FakeClock::new();
+        let start = clock.now();
+
+        let result = wait_for_session_state(
+            &context,
+            session_id,
+            SessionState::SessionStateIdle as jbyte,
+            Duration::from_secs(1),
+            &clock,
+        )
+        .unwrap();
+        assert!(result);
+        assert_eq!(clock.now() - start, SESSION_STATE_POLL_INITIAL_INTERVAL * 3);
+        assert_eq!(context.session_status_lock_count(), 3);
+    }
+
+    #[test]
+    fn test_wait_for_session_state_failed_status() {
+        let session_id = 1234;
+        let mut dispatcher = MockDispatcher::new();
+        let packet = uwb_uci_packets::SessionGetStateRspBuilder {
+            status: StatusCode::UciStatusSessionNotExist,
+            session_state: SessionState::SessionStateIdle,
+        }
+        .build();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetSessionState(session_id),
+            Ok(UciResponse::SessionGetStateRsp(packet)),
+        );
+        let context = MockContext::new(dispatcher);
+
+        // The reported state is meaningless when the query failed.
+        let result = wait_for_session_state(
+            &context,
+            session_id,
+            SessionState::SessionStateIdle as jbyte,
+            Duration::from_secs(1),
+            &FakeClock::new(),
+        );
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusSessionNotExist))));
+    }
+
+    #[test]
+    fn test_wait_for_session_state_timeout() {
+        let session_id = 1234;
+        let mut dispatcher = MockDispatcher::new();
+        for _ in 0..8 {
+            expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateInit);
+        }
+        let context = MockContext::new(dispatcher);
+        let clock = FakeClock::new();
+        let start = clock.now();
+
+        // Queried at 0, 50, 150, 350, 750, 1550, 2550 and 3000 ms: the interval doubles up to 1 s
+        // and the last sleep is cut short by the deadline.
+        let result = wait_for_session_state(
+            &context,
+            session_id,
+            SessionState::SessionStateIdle as jbyte,
+            Duration::from_secs(3),
+            &clock,
+        )
+        .unwrap();
+        assert!(!result);
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+        assert_eq!(context.session_status_lock_count(), 8);
+    }
+
+    #[test]
+    fn test_wait_for_session_state_timeout_capped() {
+        let session_id = 1234;
+        let mut dispatcher = MockDispatcher::new();
+        // Queried at 0, 50, 150, 350, 750, 1550 ms, then every second up to 10 s.
+        for _ in 0..15 {
+            expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateInit);
+        }
+        let context = MockContext::new(dispatcher);
+        let clock = FakeClock::new();
+        let start = clock.now();
+
+        let result = wait_for_session_state(
+            &context,
+            session_id,
+            SessionState::SessionStateIdle as jbyte,
+            Duration::from_secs(3600),
+            &clock,
+        )
+        .unwrap();
+        assert!(!result);
+        assert_eq!(clock.now() - start, SESSION_STATE_WAIT_MAX_TIMEOUT);
+    }
+
     #[test]
     fn test_set_app_configurations() {
         let session_id = 1234;
         let no_of_params = 3;
-        let app_config_param_len = 5;
-        let app_configs = vec![1, 2, 3, 4, 5];
+        let app_config_param_len = 9;
+        let app_configs = vec![0x00, 1, 1, 0x11, 1, 0, 0x04, 1, 9];
         let fake_app_config_params = std::ptr::null_mut();
         let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
             status: StatusCode::UciStatusOk,
@@ -1112,11 +3034,373 @@ mod tests {
             no_of_params,
             app_config_param_len,
             fake_app_config_params,
+            false,
         )
         .unwrap();
         assert_eq!(result.to_vec(), packet.to_vec());
     }
 
+    #[test]
+    fn test_set_app_configurations_records_unconfirmed() {
+        let session_id = 1234;
+        let app_configs = vec![0x04, 1, 0x42, 0x11, 1, 0x00];
+        let fake_app_config_params = std::ptr::null_mut();
+        let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusInvalidParam,
+            cfg_status: vec![uwb_uci_packets::AppConfigStatus {
+                cfg_id: AppConfigTlvType::ChannelNumber,
+                status: StatusCode::UciStatusInvalidRange,
+            }],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 2,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs: app_configs.clone(),
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.init_session(session_id, 0x00);
+        context.expect_convert_byte_array(fake_app_config_params, Ok(app_configs.clone()));
+
+        set_app_configurations(
+            &context,
+            session_id,
+            2,
+            app_configs.len() as u32,
+            fake_app_config_params,
+        )
+        .unwrap();
+        assert_eq!(
+            list_configured_app_config_ids(&context, session_id).unwrap(),
+            vec![
+                (0x04, StatusCode::UciStatusInvalidRange as u8),
+                (0x11, StatusCode::UciStatusInvalidParam as u8)
+            ]
+        );
+        let cache = &context.get_caches().unwrap().app_configs;
+        assert!(cache.get(session_id, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_app_configurations_cached() {
+        let session_id = 1234;
+        let app_configs = vec![0x02, 1, 0x01, 0x11, 1, 0];
+        let fake_app_config_params = std::ptr::null_mut();
+        let set_packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 2,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs: app_configs.clone(),
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(set_packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context.expect_convert_byte_array(fake_app_config_params, Ok(app_configs.clone()));
+        context.expect_convert_byte_array(fake_app_config_params, Ok(vec![0x11]));
+        context.expect_convert_byte_array(fake_app_config_params, Ok(vec![0x11]));
+
+        set_app_configurations(
+            &context,
+            session_id,
+            2,
+            app_configs.len() as u32,
+            fake_app_config_params,
+        )
+        .unwrap();
+        // No UCI command is expected for the cached read.
+        let result =
+            get_app_configurations(&context, session_id, 1, 1, fake_app_config_params, true)
+                .unwrap();
+        let expected = uwb_uci_packets::SessionGetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            tlvs: vec![AppConfigTlv { cfg_id: AppConfigTlvType::DeviceRole, v: vec![0] }],
+        }
+        .build();
+        assert_eq!(result.to_vec(), expected.to_vec());
+
+        context.get_caches().unwrap().app_configs.remove(session_id);
+        assert!(get_app_configurations(&context, session_id, 1, 1, fake_app_config_params, true)
+            .is_err());
+    }
+
+    fn expect_session_state(dispatcher: &mut MockDispatcher, session_id: u32, state: SessionState) {
+        let packet = uwb_uci_packets::SessionGetStateRspBuilder {
+            status: StatusCode::UciStatusOk,
+            session_state: state,
+        }
+        .build();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetSessionState(session_id),
+            Ok(UciResponse::SessionGetStateRsp(packet)),
+        );
+    }
+
+    #[test]
+    fn test_reconfigure_session() {
+        let session_id = 1234;
+        let fake_app_config_params = std::ptr::null_mut();
+        let delta = vec![0x2D, 1, 0x02];
+        let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateActive);
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 1,
+                app_config_param_len: delta.len() as u32,
+                app_configs: delta,
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context
+            .get_caches()
+            .unwrap()
+            .app_configs
+            .update(session_id, &[(0x0E, &[0x00][..]), (0x2D, &[0x00][..])]);
+        context.expect_convert_byte_array(
+            fake_app_config_params,
+            Ok(vec![0x0E, 1, 0x00, 0x2D, 1, 0x02]),
+        );
+        context.expect_convert_byte_array(
+            fake_app_config_params,
+            Ok(vec![0x0E, 1, 0x00, 0x2D, 1, 0x02]),
+        );
+
+        let (status, applied) =
+            reconfigure_session(&context, session_id, fake_app_config_params).unwrap();
+        assert_eq!(status, StatusCode::UciStatusOk);
+        assert_eq!(applied, vec![(0x2D, vec![0x02])]);
+        // Nothing changed since, so no UCI command is expected.
+        let (status, applied) =
+            reconfigure_session(&context, session_id, fake_app_config_params).unwrap();
+        assert_eq!(status, StatusCode::UciStatusOk);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn test_reconfigure_session_rejected_while_active() {
+        let session_id = 1234;
+        let fake_app_config_params = std::ptr::null_mut();
+        let mut dispatcher = MockDispatcher::new();
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateActive);
+        let mut context = MockContext::new(dispatcher);
+        // CHANNEL_NUMBER cannot be changed during ranging.
+        context.expect_convert_byte_array(fake_app_config_params, Ok(vec![0x04, 1, 9]));
+
+        let result = reconfigure_session(&context, session_id, fake_app_config_params);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusRejected))));
+    }
+
+    #[test]
+    fn test_reconfigure_session_sts_config_with_unchanged_keys() {
+        let session_id = 1234;
+        let fake_app_config_params = std::ptr::null_mut();
+        let vendor_id = [0x27, 2, 0x08, 0x07];
+        let static_sts_iv = [0x28, 6, 1, 2, 3, 4, 5, 6];
+        let delta = vec![0x02, 1, 0x00];
+        let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        expect_session_state(&mut dispatcher, session_id, SessionState::SessionStateIdle);
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 1,
+                app_config_param_len: delta.len() as u32,
+                app_configs: delta,
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.update(
+            session_id,
+            &[(0x02, &[0x01][..]), (0x27, &vendor_id[2..]), (0x28, &static_sts_iv[2..])],
+        );
+        // Switching to static STS only sends STS_CONFIG, the keys it requires are already set.
+        context.expect_convert_byte_array(
+            fake_app_config_params,
+            Ok([&[0x02, 1, 0x00][..], &vendor_id, &static_sts_iv].concat()),
+        );
+
+        let (status, applied) =
+            reconfigure_session(&context, session_id, fake_app_config_params).unwrap();
+        assert_eq!(status, StatusCode::UciStatusOk);
+        assert_eq!(applied, vec![(0x02, vec![0x00])]);
+    }
+
+    #[test]
+    fn test_range_data_ntf_tlvs() {
+        let (azimuth, elevation) = ((-180.0, 180.0), (-90.0, 90.0));
+        let tlvs = range_data_ntf_tlvs(2, 100, 0x1234, azimuth, elevation).unwrap();
+        assert_eq!(
+            encode_app_config_tlvs(&tlvs),
+            vec![0x0E, 1, 2, 0x0F, 2, 100, 0, 0x10, 2, 0x34, 0x12]
+        );
+        assert!(range_data_ntf_tlvs(8, 0, 100, azimuth, elevation).is_err());
+        assert!(range_data_ntf_tlvs(2, 200, 100, azimuth, elevation).is_err());
+        assert!(range_data_ntf_tlvs(2, -1, 100, azimuth, elevation).is_err());
+        assert!(range_data_ntf_tlvs(2, 0, 0x10000, azimuth, elevation).is_err());
+    }
+
+    #[test]
+    fn test_range_data_ntf_tlvs_aoa_bound() {
+        // -90.5, 45, -10 and 0.25 degrees in Q9.7.
+        let tlvs = range_data_ntf_tlvs(3, 0, 100, (-90.5, 45.0), (-10.0, 0.25)).unwrap();
+        assert_eq!(
+            encode_app_config_tlvs(&tlvs),
+            vec![
+                0x0E, 1, 3, 0x0F, 2, 0, 0, 0x10, 2, 100, 0, 0x1D, 8, 0xC0, 0xD2, 0x80, 0x16, 0x00,
+                0xFB, 0x20, 0x00,
+            ]
+        );
+        assert!(range_data_ntf_tlvs(3, 0, 100, (-181.0, 0.0), (-90.0, 90.0)).is_err());
+        assert!(range_data_ntf_tlvs(3, 0, 100, (0.0, 180.5), (-90.0, 90.0)).is_err());
+        assert!(range_data_ntf_tlvs(3, 0, 100, (10.0, -10.0), (-90.0, 90.0)).is_err());
+        assert!(range_data_ntf_tlvs(3, 0, 100, (-180.0, 180.0), (-91.0, 0.0)).is_err());
+        assert!(range_data_ntf_tlvs(3, 0, 100, (-180.0, 180.0), (20.0, 10.0)).is_err());
+        assert!(range_data_ntf_tlvs(3, 0, 100, (f32::NAN, 0.0), (-90.0, 90.0)).is_err());
+        // Out of range bounds are rejected even when the config does not use them.
+        assert!(range_data_ntf_tlvs(1, 0, 100, (-180.0, 180.0), (-91.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_parse_app_config_tlvs() {
+        assert_eq!(
+            parse_app_config_tlvs(&[0x02, 1, 0, 0x27, 2, 0x08, 0x07]).unwrap(),
+            vec![(0x02, &[0u8][..]), (0x27, &[0x08, 0x07][..])]
+        );
+        assert!(parse_app_config_tlvs(&[]).unwrap().is_empty());
+        assert!(parse_app_config_tlvs(&[0x02]).is_err());
+        assert!(parse_app_config_tlvs(&[0x27, 2, 0x08]).is_err());
+    }
+
+    #[test]
+    fn test_validate_sts_configs() {
+        let vendor_id = [0x27, 2, 0x08, 0x07];
+        let static_sts_iv = [0x28, 6, 1, 2, 3, 4, 5, 6];
+        let sub_session_id = [0x30, 4, 1, 2, 3, 4];
+        let validate =
+            |tlvs: &[&[u8]]| validate_sts_configs(&parse_app_config_tlvs(&tlvs.concat()).unwrap());
+
+        assert!(validate(&[]).is_ok());
+        assert!(validate(&[&[0x02, 1, 0x00], &vendor_id, &static_sts_iv]).is_ok());
+        assert!(validate(&[&[0x02, 1, 0x00], &vendor_id]).is_err());
+        assert!(validate(&[&[0x02, 1, 0x01]]).is_ok());
+        assert!(validate(&[&[0x02, 1, 0x02], &sub_session_id]).is_ok());
+        assert!(validate(&[&[0x02, 1, 0x02]]).is_err());
+        assert!(validate(&[&[0x02, 1, 0x03]]).is_err());
+        assert!(validate(&[&[0x02, 2, 0x00, 0x00]]).is_err());
+        // Key lengths are checked even when STS_CONFIG is not part of the request.
+        assert!(validate(&[&vendor_id]).is_ok());
+        assert!(validate(&[&[0x28, 4, 1, 2, 3, 4]]).is_err());
+        assert!(validate(&[&[0x30, 2, 1, 2]]).is_err());
+    }
+
+    #[test]
+    fn test_set_app_configurations_unknown_session() {
+        let session_id = 1234;
+        let app_configs = vec![0x11, 1, 0x00];
+        let fake_app_config_params = std::ptr::null_mut();
+        let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusSessionNotExist,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 1,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs: app_configs.clone(),
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(packet)),
+        );
+        let mut context = MockContext::new(dispatcher);
+        context.expect_convert_byte_array(fake_app_config_params, Ok(app_configs.clone()));
+
+        set_app_configurations(
+            &context,
+            session_id,
+            1,
+            app_configs.len() as u32,
+            fake_app_config_params,
+        )
+        .unwrap();
+        // The failure is not cached for a session the chip does not know.
+        assert_eq!(context.get_caches().unwrap().app_configs.session_count(), 0);
+        assert!(list_configured_app_config_ids(&context, session_id).is_err());
+    }
+
+    #[test]
+    fn test_set_app_configurations_invalid_sts() {
+        let fake_app_config_params = std::ptr::null_mut();
+        let mut context = MockContext::new(MockDispatcher::new());
+        context.expect_convert_byte_array(fake_app_config_params, Ok(vec![0x02, 1, 0x02]));
+
+        let result = set_app_configurations(&context, 1234, 1, 3, fake_app_config_params);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))));
+    }
+
+    #[test]
+    fn test_configure_range_data_ntf() {
+        let session_id = 1234;
+        let app_configs = vec![0x0E, 1, 2, 0x0F, 2, 100, 0, 0x10, 2, 200, 0];
+        let packet = uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciSetAppConfig {
+                session_id,
+                no_of_params: 3,
+                app_config_param_len: app_configs.len() as u32,
+                app_configs,
+            },
+            Ok(UciResponse::SessionSetAppConfigRsp(packet)),
+        );
+        let context = MockContext::new(dispatcher);
+
+        let result = configure_range_data_ntf(
+            &context,
+            session_id,
+            2,
+            100,
+            200,
+            (-180.0, 180.0),
+            (-90.0, 90.0),
+        );
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_get_caps_info() {
         let packet = uwb_uci_packets::GetCapsInfoRspBuilder {
@@ -1134,6 +3418,29 @@ mod tests {
 
         let result = get_caps_info(&context).unwrap();
         assert_eq!(result.to_vec(), packet.to_vec());
+        // Served from the cache, nothing else is sent.
+        let result = get_caps_info(&context).unwrap();
+        assert_eq!(result.to_vec(), packet.to_vec());
+    }
+
+    #[test]
+    fn test_get_caps_info_failed_status_not_cached() {
+        let packet = uwb_uci_packets::GetCapsInfoRspBuilder {
+            status: StatusCode::UciStatusFailed,
+            tlvs: vec![],
+        }
+        .build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciGetCapsInfo,
+            Ok(UciResponse::GetCapsInfoRsp(packet.clone())),
+        );
+        let context = MockContext::new(dispatcher);
+
+        let result = get_caps_info(&context).unwrap();
+        assert_eq!(result.get_status(), StatusCode::UciStatusFailed);
+        assert!(context.get_caches().unwrap().caps_info.lock().unwrap().is_none());
     }
 
     #[test]
@@ -1179,6 +3486,53 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_controlees() {
+        assert_eq!(
+            validate_controlees(3, &[1, 2, 1], &[10, 20, 10]).unwrap(),
+            (vec![1, 2], vec![10, 20])
+        );
+        assert!(validate_controlees(2, &[1, 2, 3], &[10, 20, 30]).is_err());
+        assert!(validate_controlees(2, &[1, 2], &[10]).is_err());
+        assert!(validate_controlees(1, &[0], &[10]).is_err());
+        assert!(validate_controlees(1, &[-1], &[10]).is_err());
+        assert!(validate_controlees(2, &[1, 1], &[10, 20]).is_err());
+    }
+
+    #[test]
+    fn test_validate_controlee_sub_session_ids() {
+        assert!(validate_controlee_sub_session_ids(None, &[0, 10]).is_ok());
+        assert!(validate_controlee_sub_session_ids(Some(0x02), &[10, 20]).is_ok());
+        assert!(validate_controlee_sub_session_ids(Some(0x02), &[10, 0]).is_err());
+        assert!(validate_controlee_sub_session_ids(Some(0x01), &[0, 0]).is_ok());
+        assert!(validate_controlee_sub_session_ids(Some(0x01), &[0, 10]).is_err());
+        assert!(validate_controlee_sub_session_ids(Some(0x00), &[10]).is_err());
+    }
+
+    #[test]
+    fn test_multicast_list_update_missing_sub_session_ids() {
+        let session_id = 1234;
+        let fake_addresses = std::ptr::null_mut();
+        let address_list = Box::new([1, 3]);
+        let fake_sub_session_ids = std::ptr::null_mut();
+        let sub_session_id_list = Box::new([0, 0]);
+
+        let mut context = MockContext::new(MockDispatcher::new());
+        context.expect_get_array_length(fake_addresses, Ok(address_list.len() as jsize));
+        context.expect_get_short_array_region(fake_addresses, 0, Ok(address_list));
+        context
+            .expect_get_array_length(fake_sub_session_ids, Ok(sub_session_id_list.len() as jsize));
+        context.expect_get_int_array_region(fake_sub_session_ids, 0, Ok(sub_session_id_list));
+        let app_configs = &context.get_caches().unwrap().app_configs;
+        app_configs.init_session(session_id, 0x00);
+        app_configs.update(session_id, &[(0x02, &[0x02][..])]);
+
+        // Rejected before any command reaches the UWBS.
+        let result =
+            multicast_list_update(&context, session_id, 0, 2, fake_addresses, fake_sub_session_ids);
+        assert!(matches!(result, Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))));
+    }
+
     #[test]
     fn test_set_country_code() {
         let fake_country_code = std::ptr::null_mut();
@@ -1271,8 +3625,36 @@ mod tests {
             Ok(UciResponse::DeviceResetRsp(packet)),
         );
         let context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.init_session(1234, 0x00);
+        *context.get_caches().unwrap().caps_info.lock().unwrap() = Some(
+            uwb_uci_packets::GetCapsInfoRspBuilder {
+                status: StatusCode::UciStatusOk,
+                tlvs: vec![],
+            }
+            .build(),
+        );
 
         let result = reset_device(&context, reset_config);
         assert!(result.is_ok());
+        assert_eq!(context.get_caches().unwrap().app_configs.session_count(), 0);
+        assert!(context.get_caches().unwrap().caps_info.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reset_device_failed_keeps_caches() {
+        let reset_config = uwb_uci_packets::ResetConfig::UwbsReset as u8;
+        let packet =
+            uwb_uci_packets::DeviceResetRspBuilder { status: StatusCode::UciStatusFailed }.build();
+
+        let mut dispatcher = MockDispatcher::new();
+        dispatcher.expect_block_on_jni_command(
+            JNICommand::UciDeviceReset { reset_config },
+            Ok(UciResponse::DeviceResetRsp(packet)),
+        );
+        let context = MockContext::new(dispatcher);
+        context.get_caches().unwrap().app_configs.init_session(1234, 0x00);
+
+        assert!(reset_device(&context, reset_config).is_err());
+        assert_eq!(context.get_caches().unwrap().app_configs.session_count(), 1);
     }
 }
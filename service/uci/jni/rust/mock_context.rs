@@ -1,29 +1,42 @@
 use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 
-use jni::sys::{jarray, jbyteArray, jint, jintArray, jshort, jshortArray, jsize};
+use jni::sys::{jarray, jbyte, jbyteArray, jint, jintArray, jshort, jshortArray, jsize};
 use uwb_uci_rust::error::UwbErr;
 use uwb_uci_rust::uci::Dispatcher;
 
 use crate::mock_dispatcher::MockDispatcher;
+use crate::native_stack::StackCaches;
 use crate::Context;
 
 #[cfg(test)]
 pub struct MockContext {
     dispatcher: Cell<MockDispatcher>,
+    caches: StackCaches,
     expected_calls: RefCell<VecDeque<ExpectedCall>>,
+    session_status_lock_count: Cell<usize>,
 }
 
 #[cfg(test)]
 impl MockContext {
     pub fn new(dispatcher: MockDispatcher) -> Self {
-        Self { dispatcher: Cell::new(dispatcher), expected_calls: Default::default() }
+        Self {
+            dispatcher: Cell::new(dispatcher),
+            caches: StackCaches::new(),
+            expected_calls: Default::default(),
+            session_status_lock_count: Cell::new(0),
+        }
     }
 
     pub fn get_mock_dispatcher(&mut self) -> &mut MockDispatcher {
         self.dispatcher.get_mut()
     }
 
+    /// Number of times the session status lock has been taken.
+    pub fn session_status_lock_count(&self) -> usize {
+        self.session_status_lock_count.get()
+    }
+
     pub fn expect_convert_byte_array(
         &mut self,
         expected_array: jbyteArray,
@@ -162,6 +175,18 @@ impl<'a> Context<'a> for MockContext {
     fn get_dispatcher(&self) -> Result<&'a mut dyn Dispatcher, UwbErr> {
         unsafe { Ok(&mut *(self.dispatcher.as_ptr())) }
     }
+
+    fn get_caches(&self) -> Result<&StackCaches, UwbErr> {
+        Ok(&self.caches)
+    }
+
+    fn with_session_status_lock(
+        &self,
+        f: &mut dyn FnMut() -> Result<jbyte, UwbErr>,
+    ) -> Result<jbyte, UwbErr> {
+        self.session_status_lock_count.set(self.session_status_lock_count.get() + 1);
+        f()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,79 @@
+//! Native state owned by one NativeUwbManager.
+//!
+//! The handle Java stores in mDispatcherPointer refers to the dispatcher together with the caches
+//! built from its UCI traffic. Keeping them in one object ties the caches to the UWBS they
+//! describe: they are released with the dispatcher and never shared between dispatchers.
+
+use std::sync::Mutex;
+
+use uwb_uci_packets::GetCapsInfoRspPacket;
+
+use crate::app_config_cache::AppConfigCache;
+
+pub struct NativeStack<D> {
+    pub dispatcher: D,
+    pub caches: StackCaches,
+}
+
+impl<D> NativeStack<D> {
+    pub fn new(dispatcher: D) -> Self {
+        Self { dispatcher, caches: StackCaches::new() }
+    }
+}
+
+/// State of the UWBS mirrored natively, only valid as long as the UWBS keeps that state.
+pub struct StackCaches {
+    pub app_configs: AppConfigCache,
+    /// Last successful GetCapsInfo response, fetched while initializing the UWBS.
+    pub caps_info: Mutex<Option<GetCapsInfoRspPacket>>,
+}
+
+impl StackCaches {
+    pub fn new() -> Self {
+        Self { app_configs: AppConfigCache::new(), caps_info: Mutex::new(None) }
+    }
+
+    /// Drop everything cached, for when the UWBS lost its state, e.g. on reset or power off.
+    pub fn clear(&self) {
+        self.app_configs.clear();
+        *self.caps_info.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use uwb_uci_packets::{GetCapsInfoRspBuilder, StatusCode};
+
+    use crate::handle_table::HandleTable;
+
+    #[test]
+    fn test_clear() {
+        let caches = StackCaches::new();
+        caches.app_configs.init_session(1, 0x00);
+        caches.app_configs.update(1, &[(0x02, &[0x00][..])]);
+        *caches.caps_info.lock().unwrap() =
+            Some(GetCapsInfoRspBuilder { status: StatusCode::UciStatusOk, tlvs: vec![] }.build());
+        caches.clear();
+        assert_eq!(caches.app_configs.session_count(), 0);
+        assert!(caches.app_configs.get(1, &[]).is_none());
+        assert!(caches.caps_info.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_caches_released_with_handle() {
+        let table = HandleTable::new();
+        let handle = table.insert(Box::new(NativeStack::new(())));
+        // Safety: the handle is live and nothing else accesses the object.
+        let stack = unsafe { &*table.get(handle).unwrap() };
+        stack.caches.app_configs.update(1, &[(0x02, &[0x00][..])]);
+        assert!(table.remove(handle).is_some());
+
+        // A new dispatcher starts from empty caches.
+        let handle = table.insert(Box::new(NativeStack::new(())));
+        // Safety: the handle is live and nothing else accesses the object.
+        let stack = unsafe { &*table.get(handle).unwrap() };
+        assert_eq!(stack.caches.app_configs.session_count(), 0);
+    }
+}
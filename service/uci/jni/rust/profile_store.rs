@@ -0,0 +1,62 @@
+//! Provisioned session profiles, identified by a profile id.
+//!
+//! A profile is a session config blob as produced by session_config_blob. Profiles are only kept
+//! in memory, the Java layer stores them again whenever the native stack is loaded.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::session_config_blob::SessionConfigBlob;
+
+pub struct ProfileStore {
+    profiles: Mutex<BTreeMap<u32, SessionConfigBlob>>,
+}
+
+impl ProfileStore {
+    pub fn new() -> Self {
+        Self { profiles: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Store the profile, replacing any profile with the same id.
+    pub fn store(&self, profile_id: u32, profile: SessionConfigBlob) {
+        self.profiles.lock().unwrap().insert(profile_id, profile);
+    }
+
+    /// Return the ids of all stored profiles in ascending order.
+    pub fn ids(&self) -> Vec<u32> {
+        self.profiles.lock().unwrap().keys().copied().collect()
+    }
+
+    pub fn get(&self, profile_id: u32) -> Option<SessionConfigBlob> {
+        self.profiles.lock().unwrap().get(&profile_id).cloned()
+    }
+
+    /// Remove the profile, returning whether it was stored.
+    pub fn remove(&self, profile_id: u32) -> bool {
+        self.profiles.lock().unwrap().remove(&profile_id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(session_type: u8) -> SessionConfigBlob {
+        SessionConfigBlob { session_type, uci_version: 0x1001, app_configs: vec![0x02, 1, 0x01] }
+    }
+
+    #[test]
+    fn test_store_get_remove() {
+        let store = ProfileStore::new();
+        store.store(7, profile(0x00));
+        store.store(3, profile(0x00));
+        store.store(7, profile(0x01));
+        assert_eq!(store.ids(), vec![3, 7]);
+        assert_eq!(store.get(7), Some(profile(0x01)));
+
+        assert!(store.remove(7));
+        assert!(!store.remove(7));
+        assert_eq!(store.get(7), None);
+        assert_eq!(store.ids(), vec![3]);
+    }
+}
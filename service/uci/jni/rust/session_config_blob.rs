@@ -0,0 +1,89 @@
+//! Portable snapshot of a session's configuration.
+//!
+//! Layout, all multi-byte fields little endian:
+//!   byte 0      format version, currently 1
+//!   byte 1      session type the session was initialized with
+//!   bytes 2-3   UCI version reported by the exporting UWBS
+//!   bytes 4..   app config TLVs, encoded as in a set app config command
+
+use log::error;
+use uwb_uci_packets::StatusCode;
+use uwb_uci_rust::error::UwbErr;
+
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionConfigBlob {
+    pub session_type: u8,
+    pub uci_version: u16,
+    pub app_configs: Vec<u8>,
+}
+
+impl SessionConfigBlob {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN + self.app_configs.len());
+        buf.push(FORMAT_VERSION);
+        buf.push(self.session_type);
+        buf.extend_from_slice(&self.uci_version.to_le_bytes());
+        buf.extend(&self.app_configs);
+        buf
+    }
+
+    pub fn decode(blob: &[u8]) -> Result<Self, UwbErr> {
+        match blob {
+            [FORMAT_VERSION, session_type, v0, v1, app_configs @ ..] => Ok(Self {
+                session_type: *session_type,
+                uci_version: u16::from_le_bytes([*v0, *v1]),
+                app_configs: app_configs.to_vec(),
+            }),
+            [version, ..] if *version != FORMAT_VERSION => {
+                error!("Unsupported session config blob version {}", version);
+                Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+            }
+            _ => {
+                error!("Session config blob too short: {} bytes", blob.len());
+                Err(UwbErr::StatusCode(StatusCode::UciStatusInvalidParam))
+            }
+        }
+    }
+
+    /// Whether the configs were exported from a UWBS speaking the same UCI major version as the
+    /// local one, the first octet of the version on the wire.
+    pub fn is_compatible_with(&self, local_uci_version: u16) -> bool {
+        self.uci_version & 0xFF == local_uci_version & 0xFF
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let blob = SessionConfigBlob {
+            session_type: 0x00,
+            uci_version: 0x1001,
+            app_configs: vec![0x02, 1, 0x01],
+        };
+        let encoded = blob.encode();
+        assert_eq!(encoded, vec![0x01, 0x00, 0x01, 0x10, 0x02, 1, 0x01]);
+        assert_eq!(SessionConfigBlob::decode(&encoded).unwrap(), blob);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(SessionConfigBlob::decode(&[]).is_err());
+        assert!(SessionConfigBlob::decode(&[0x01, 0x00, 0x01]).is_err());
+        assert!(SessionConfigBlob::decode(&[0x02, 0x00, 0x01, 0x10]).is_err());
+    }
+
+    #[test]
+    fn test_is_compatible_with() {
+        let blob =
+            SessionConfigBlob { session_type: 0x00, uci_version: 0x1001, app_configs: vec![] };
+        assert!(blob.is_compatible_with(0x1001));
+        assert!(blob.is_compatible_with(0x2001));
+        assert!(!blob.is_compatible_with(0x1002));
+    }
+}
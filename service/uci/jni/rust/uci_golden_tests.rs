@@ -0,0 +1,446 @@
+//! Golden UCI byte vectors for the packets exchanged by the JNI layer.
+//!
+//! Every command emitted on behalf of a JNI entry point and every response consumed by one is
+//! built with the uwb_uci_packets builders and compared byte for byte against the encoding
+//! described in the FiRa UCI specification, so that packet regressions are caught on host.
+
+use uwb_uci_packets::{
+    AppConfigStatus, AppConfigTlv, AppConfigTlvType, CapTlv, CapTlvType, Controlee, Packet,
+    ResetConfig, SessionState, SessionType, StatusCode, UpdateMulticastListAction,
+};
+
+fn assert_golden(packet: Vec<u8>, golden: &[u8]) {
+    assert_eq!(packet, golden.to_vec(), "packet {:02x?} != golden {:02x?}", packet, golden);
+}
+
+#[test]
+fn test_golden_get_device_info_cmd() {
+    assert_golden(
+        uwb_uci_packets::GetDeviceInfoCmdBuilder {}.build().to_vec(),
+        &[0x20, 0x02, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_get_caps_info_cmd() {
+    assert_golden(
+        uwb_uci_packets::GetCapsInfoCmdBuilder {}.build().to_vec(),
+        &[0x20, 0x03, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_device_reset_cmd() {
+    assert_golden(
+        uwb_uci_packets::DeviceResetCmdBuilder { reset_config: ResetConfig::UwbsReset }
+            .build()
+            .to_vec(),
+        &[0x20, 0x00, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_init_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionInitCmdBuilder {
+            session_id: 0x04030201,
+            session_type: SessionType::FiraRangingSession,
+        }
+        .build()
+        .to_vec(),
+        &[0x21, 0x00, 0x00, 0x05, 0x01, 0x02, 0x03, 0x04, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_deinit_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionDeinitCmdBuilder { session_id: 0x04030201 }.build().to_vec(),
+        &[0x21, 0x01, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04],
+    );
+}
+
+#[test]
+fn test_golden_session_get_count_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionGetCountCmdBuilder {}.build().to_vec(),
+        &[0x21, 0x05, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_get_state_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionGetStateCmdBuilder { session_id: 0x04030201 }.build().to_vec(),
+        &[0x21, 0x06, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04],
+    );
+}
+
+#[test]
+fn test_golden_session_set_app_config_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionSetAppConfigCmdBuilder {
+            session_id: 0x04030201,
+            tlvs: vec![
+                AppConfigTlv { cfg_id: AppConfigTlvType::DeviceRole, v: vec![0x01] },
+                AppConfigTlv { cfg_id: AppConfigTlvType::ChannelNumber, v: vec![0x09] },
+            ],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x21, 0x03, 0x00, 0x0B, // header
+            0x01, 0x02, 0x03, 0x04, // session_id
+            0x02, // number of tlvs
+            0x11, 0x01, 0x01, // DEVICE_ROLE
+            0x04, 0x01, 0x09, // CHANNEL_NUMBER
+        ],
+    );
+}
+
+#[test]
+fn test_golden_session_get_app_config_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionGetAppConfigCmdBuilder {
+            session_id: 0x04030201,
+            app_cfg: vec![0x11, 0x04],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x21, 0x04, 0x00, 0x07, // header
+            0x01, 0x02, 0x03, 0x04, // session_id
+            0x02, 0x11, 0x04, // config ids
+        ],
+    );
+}
+
+#[test]
+fn test_golden_session_update_controller_multicast_list_cmd() {
+    assert_golden(
+        uwb_uci_packets::SessionUpdateControllerMulticastListCmdBuilder {
+            session_id: 0x04030201,
+            action: UpdateMulticastListAction::AddControlee,
+            controlees: vec![
+                Controlee { short_address: 0xB0A0, subsession_id: 0x08070605 },
+                Controlee { short_address: 0xB1A1, subsession_id: 0x0C0B0A09 },
+            ],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x21, 0x07, 0x00, 0x12, // header
+            0x01, 0x02, 0x03, 0x04, // session_id
+            0x00, // action
+            0x02, // number of controlees
+            0xA0, 0xB0, 0x05, 0x06, 0x07, 0x08, // short address, sub-session id
+            0xA1, 0xB1, 0x09, 0x0A, 0x0B, 0x0C, // short address, sub-session id
+        ],
+    );
+}
+
+#[test]
+fn test_golden_range_start_cmd() {
+    assert_golden(
+        uwb_uci_packets::RangeStartCmdBuilder { session_id: 0x04030201 }.build().to_vec(),
+        &[0x22, 0x00, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04],
+    );
+}
+
+#[test]
+fn test_golden_range_stop_cmd() {
+    assert_golden(
+        uwb_uci_packets::RangeStopCmdBuilder { session_id: 0x04030201 }.build().to_vec(),
+        &[0x22, 0x01, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04],
+    );
+}
+
+#[test]
+fn test_golden_android_get_power_stats_cmd() {
+    assert_golden(
+        uwb_uci_packets::AndroidGetPowerStatsCmdBuilder {}.build().to_vec(),
+        &[0x2C, 0x00, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_android_set_country_code_cmd() {
+    assert_golden(
+        uwb_uci_packets::AndroidSetCountryCodeCmdBuilder { country_code: *b"US" }.build().to_vec(),
+        &[0x2C, 0x01, 0x00, 0x02, 0x55, 0x53],
+    );
+}
+
+#[test]
+fn test_golden_vendor_9_cmd() {
+    assert_golden(
+        uwb_uci_packets::UciVendor_9_CommandBuilder {
+            opcode: 0x04,
+            payload: Some(vec![0x01, 0x02, 0x04, 0x08].into()),
+        }
+        .build()
+        .to_vec(),
+        &[0x29, 0x04, 0x00, 0x04, 0x01, 0x02, 0x04, 0x08],
+    );
+}
+
+#[test]
+fn test_golden_device_reset_rsp() {
+    assert_golden(
+        uwb_uci_packets::DeviceResetRspBuilder { status: StatusCode::UciStatusOk }.build().to_vec(),
+        &[0x40, 0x00, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_get_device_info_rsp() {
+    assert_golden(
+        uwb_uci_packets::GetDeviceInfoRspBuilder {
+            status: StatusCode::UciStatusOk,
+            uci_version: 0x1234,
+            mac_version: 0x5678,
+            phy_version: 0x9ABC,
+            uci_test_version: 0x1357,
+            vendor_spec_info: vec![0xAA, 0xBB],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x40, 0x02, 0x00, 0x0C, // header
+            0x00, // status
+            0x34, 0x12, // uci_version
+            0x78, 0x56, // mac_version
+            0xBC, 0x9A, // phy_version
+            0x57, 0x13, // uci_test_version
+            0x02, 0xAA, 0xBB, // vendor_spec_info
+        ],
+    );
+}
+
+#[test]
+fn test_golden_get_caps_info_rsp() {
+    assert_golden(
+        uwb_uci_packets::GetCapsInfoRspBuilder { status: StatusCode::UciStatusOk, tlvs: vec![] }
+            .build()
+            .to_vec(),
+        &[0x40, 0x03, 0x00, 0x02, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_get_caps_info_rsp_with_tlvs() {
+    assert_golden(
+        uwb_uci_packets::GetCapsInfoRspBuilder {
+            status: StatusCode::UciStatusOk,
+            tlvs: vec![
+                CapTlv { t: CapTlvType::SupportedStsConfig, v: vec![0x03] },
+                CapTlv { t: CapTlvType::SupportedChannels, v: vec![0x0C] },
+            ],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x40, 0x03, 0x00, 0x08, // header
+            0x00, // status
+            0x02, // number of tlvs
+            0x04, 0x01, 0x03, // SUPPORTED_STS_CONFIG
+            0x0B, 0x01, 0x0C, // SUPPORTED_CHANNELS
+        ],
+    );
+}
+
+#[test]
+fn test_golden_session_init_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionInitRspBuilder { status: StatusCode::UciStatusOk }.build().to_vec(),
+        &[0x41, 0x00, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_deinit_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionDeinitRspBuilder { status: StatusCode::UciStatusOk }
+            .build()
+            .to_vec(),
+        &[0x41, 0x01, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_set_app_config_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            cfg_status: vec![],
+        }
+        .build()
+        .to_vec(),
+        &[0x41, 0x03, 0x00, 0x02, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_set_app_config_rsp_with_cfg_status() {
+    assert_golden(
+        uwb_uci_packets::SessionSetAppConfigRspBuilder {
+            status: StatusCode::UciStatusInvalidParam,
+            cfg_status: vec![
+                AppConfigStatus {
+                    cfg_id: AppConfigTlvType::ChannelNumber,
+                    status: StatusCode::UciStatusInvalidRange,
+                },
+                AppConfigStatus {
+                    cfg_id: AppConfigTlvType::DeviceRole,
+                    status: StatusCode::UciStatusRejected,
+                },
+            ],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x41, 0x03, 0x00, 0x06, // header
+            0x04, // status
+            0x02, // number of config statuses
+            0x04, 0x05, // CHANNEL_NUMBER, invalid range
+            0x11, 0x01, // DEVICE_ROLE, rejected
+        ],
+    );
+}
+
+#[test]
+fn test_golden_session_get_app_config_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionGetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            tlvs: vec![],
+        }
+        .build()
+        .to_vec(),
+        &[0x41, 0x04, 0x00, 0x02, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_session_get_app_config_rsp_with_tlvs() {
+    assert_golden(
+        uwb_uci_packets::SessionGetAppConfigRspBuilder {
+            status: StatusCode::UciStatusOk,
+            tlvs: vec![
+                AppConfigTlv { cfg_id: AppConfigTlvType::ChannelNumber, v: vec![0x09] },
+                AppConfigTlv { cfg_id: AppConfigTlvType::VendorId, v: vec![0x08, 0x07] },
+            ],
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x41, 0x04, 0x00, 0x09, // header
+            0x00, // status
+            0x02, // number of tlvs
+            0x04, 0x01, 0x09, // CHANNEL_NUMBER
+            0x27, 0x02, 0x08, 0x07, // VENDOR_ID
+        ],
+    );
+}
+
+#[test]
+fn test_golden_session_get_count_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionGetCountRspBuilder {
+            status: StatusCode::UciStatusOk,
+            session_count: 3,
+        }
+        .build()
+        .to_vec(),
+        &[0x41, 0x05, 0x00, 0x02, 0x00, 0x03],
+    );
+}
+
+#[test]
+fn test_golden_session_get_state_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionGetStateRspBuilder {
+            status: StatusCode::UciStatusOk,
+            session_state: SessionState::SessionStateActive,
+        }
+        .build()
+        .to_vec(),
+        &[0x41, 0x06, 0x00, 0x02, 0x00, 0x02],
+    );
+}
+
+#[test]
+fn test_golden_session_update_controller_multicast_list_rsp() {
+    assert_golden(
+        uwb_uci_packets::SessionUpdateControllerMulticastListRspBuilder {
+            status: StatusCode::UciStatusOk,
+        }
+        .build()
+        .to_vec(),
+        &[0x41, 0x07, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_range_start_rsp() {
+    assert_golden(
+        uwb_uci_packets::RangeStartRspBuilder { status: StatusCode::UciStatusOk }.build().to_vec(),
+        &[0x42, 0x00, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_range_stop_rsp() {
+    assert_golden(
+        uwb_uci_packets::RangeStopRspBuilder { status: StatusCode::UciStatusOk }.build().to_vec(),
+        &[0x42, 0x01, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_android_get_power_stats_rsp() {
+    assert_golden(
+        uwb_uci_packets::AndroidGetPowerStatsRspBuilder {
+            stats: uwb_uci_packets::PowerStats {
+                status: StatusCode::UciStatusOk,
+                idle_time_ms: 0x01,
+                tx_time_ms: 0x02,
+                rx_time_ms: 0x03,
+                total_wake_count: 0x04,
+            },
+        }
+        .build()
+        .to_vec(),
+        &[
+            0x4C, 0x00, 0x00, 0x11, // header
+            0x00, // status
+            0x01, 0x00, 0x00, 0x00, // idle_time_ms
+            0x02, 0x00, 0x00, 0x00, // tx_time_ms
+            0x03, 0x00, 0x00, 0x00, // rx_time_ms
+            0x04, 0x00, 0x00, 0x00, // total_wake_count
+        ],
+    );
+}
+
+#[test]
+fn test_golden_android_set_country_code_rsp() {
+    assert_golden(
+        uwb_uci_packets::AndroidSetCountryCodeRspBuilder { status: StatusCode::UciStatusOk }
+            .build()
+            .to_vec(),
+        &[0x4C, 0x01, 0x00, 0x01, 0x00],
+    );
+}
+
+#[test]
+fn test_golden_vendor_9_rsp() {
+    assert_golden(
+        uwb_uci_packets::UciVendor_9_ResponseBuilder {
+            opcode: 0x06,
+            payload: Some(vec![0x03, 0x06, 0x09].into()),
+        }
+        .build()
+        .to_vec(),
+        &[0x49, 0x06, 0x00, 0x03, 0x03, 0x06, 0x09],
+    );
+}
@@ -0,0 +1,178 @@
+//! Per-command metrics for the UCI commands sent through the dispatcher.
+//!
+//! For every command the collector keeps the number of calls, the latency of the blocking call and
+//! how the failed ones failed, either in the dispatcher or with an error status from the UWBS.
+//! Retries happen inside the dispatcher and are not visible here, a retried command only shows up
+//! as a longer latency.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use num_traits::ToPrimitive;
+use uwb_uci_packets::StatusCode;
+use uwb_uci_rust::error::UwbErr;
+
+pub struct UciMetrics {
+    commands: Mutex<BTreeMap<&'static str, CommandMetrics>>,
+    max_sessions_exceeded: Mutex<MaxSessionsExceeded>,
+}
+
+/// Session inits the UWBS rejected because it ran out of sessions.
+#[derive(Default)]
+struct MaxSessionsExceeded {
+    count: u64,
+    /// Sessions the UWBS reported as active after the last rejection, -1 if it could not be
+    /// queried. This is the limit the UWBS enforces.
+    last_uwbs_active_sessions: i32,
+    /// Session limit advertised to Java when the last rejection happened.
+    last_jni_session_limit: i32,
+}
+
+#[derive(Default)]
+struct CommandMetrics {
+    count: u64,
+    total_latency: Duration,
+    max_latency: Duration,
+    /// Number of failures for each error status, returned by the dispatcher or in the response.
+    error_statuses: BTreeMap<u8, u64>,
+    /// Number of failures without a status code, e.g. timeouts.
+    other_errors: u64,
+}
+
+impl UciMetrics {
+    pub fn new() -> Self {
+        Self {
+            commands: Mutex::new(BTreeMap::new()),
+            max_sessions_exceeded: Mutex::new(MaxSessionsExceeded::default()),
+        }
+    }
+
+    /// Record one call of the command, given the status carried by its response, if any, or the
+    /// error returned by the dispatcher.
+    pub fn record(
+        &self,
+        command: &'static str,
+        latency: Duration,
+        outcome: Result<Option<StatusCode>, &UwbErr>,
+    ) {
+        let mut commands = self.commands.lock().unwrap();
+        let metrics = commands.entry(command).or_default();
+        metrics.count += 1;
+        metrics.total_latency += latency;
+        metrics.max_latency = metrics.max_latency.max(latency);
+        let status = match outcome {
+            Ok(status) => status,
+            Err(UwbErr::StatusCode(status)) => Some(*status),
+            Err(_) => {
+                metrics.other_errors += 1;
+                None
+            }
+        };
+        if let Some(status) = status.filter(|status| *status != StatusCode::UciStatusOk) {
+            *metrics.error_statuses.entry(status.to_u8().unwrap_or(u8::MAX)).or_default() += 1;
+        }
+    }
+
+    /// Record a session init rejected with UCI_STATUS_MAX_SESSIONS_EXCEEDED, along with the number
+    /// of sessions the UWBS reported as active, -1 if unknown, and the limit advertised to Java.
+    pub fn record_max_sessions_exceeded(&self, uwbs_active_sessions: i32, jni_session_limit: i32) {
+        let mut max_sessions_exceeded = self.max_sessions_exceeded.lock().unwrap();
+        max_sessions_exceeded.count += 1;
+        max_sessions_exceeded.last_uwbs_active_sessions = uwbs_active_sessions;
+        max_sessions_exceeded.last_jni_session_limit = jni_session_limit;
+    }
+
+    /// One line per command, in the "key: value" format of the native stack info, followed by
+    /// the session init rejections if there were any.
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        for (command, metrics) in self.commands.lock().unwrap().iter() {
+            let statuses: Vec<String> = metrics
+                .error_statuses
+                .iter()
+                .map(|(status, count)| format!("0x{:02x}={}", status, count))
+                .collect();
+            // Writing to a String never fails.
+            let _ = writeln!(
+                summary,
+                concat!(
+                    "{}: count={} avg_latency_us={} max_latency_us={} ",
+                    "error_statuses=[{}] other_errors={}"
+                ),
+                command,
+                metrics.count,
+                metrics.total_latency.as_micros() / metrics.count as u128,
+                metrics.max_latency.as_micros(),
+                statuses.join(","),
+                metrics.other_errors,
+            );
+        }
+        let max_sessions_exceeded = self.max_sessions_exceeded.lock().unwrap();
+        if max_sessions_exceeded.count > 0 {
+            let _ = writeln!(
+                summary,
+                concat!(
+                    "max_sessions_exceeded: count={} last_uwbs_active_sessions={} ",
+                    "jni_session_limit={}"
+                ),
+                max_sessions_exceeded.count,
+                max_sessions_exceeded.last_uwbs_active_sessions,
+                max_sessions_exceeded.last_jni_session_limit,
+            );
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let metrics = UciMetrics::new();
+        assert_eq!(metrics.summary(), "");
+
+        metrics.record(
+            "UciSessionInit",
+            Duration::from_micros(100),
+            Ok(Some(StatusCode::UciStatusOk)),
+        );
+        metrics.record(
+            "UciSessionInit",
+            Duration::from_micros(300),
+            Err(&UwbErr::StatusCode(StatusCode::UciStatusRejected)),
+        );
+        metrics.record(
+            "UciSessionInit",
+            Duration::from_micros(200),
+            Ok(Some(StatusCode::UciStatusRejected)),
+        );
+        metrics.record("UciGetCapsInfo", Duration::from_micros(50), Err(&UwbErr::failed()));
+        metrics.record("UciRawVendorCmd", Duration::from_micros(10), Ok(None));
+        assert_eq!(
+            metrics.summary(),
+            concat!(
+                "UciGetCapsInfo: count=1 avg_latency_us=50 max_latency_us=50 error_statuses=[] ",
+                "other_errors=1\n",
+                "UciRawVendorCmd: count=1 avg_latency_us=10 max_latency_us=10 error_statuses=[] ",
+                "other_errors=0\n",
+                "UciSessionInit: count=3 avg_latency_us=200 max_latency_us=300 ",
+                "error_statuses=[0x01=2] other_errors=0\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_max_sessions_exceeded() {
+        let metrics = UciMetrics::new();
+        metrics.record_max_sessions_exceeded(3, 5);
+        metrics.record_max_sessions_exceeded(-1, 5);
+        assert_eq!(
+            metrics.summary(),
+            "max_sessions_exceeded: count=2 last_uwbs_active_sessions=-1 jni_session_limit=5\n"
+        );
+    }
+}